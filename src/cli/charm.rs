@@ -2,6 +2,7 @@ use clap::{App, ArgMatches};
 
 mod build;
 mod create;
+mod cron;
 
 use crate::cli::*;
 
@@ -20,6 +21,7 @@ impl<'a> CliCommand<'a> for CharmSubcommand {
         vec![
             Box::new(build::BuildSubcommand),
             Box::new(create::CreateSubcommand),
+            Box::new(cron::CronSubcommand),
         ]
     }
 
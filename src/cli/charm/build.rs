@@ -104,6 +104,9 @@ impl<'a> CliCommand<'a> for BuildSubcommand {
         let lucky_metadata = load_yaml::<LuckyMetadata>(&charm_path, "lucky")?;
         // Validate chron schedules
         for (schedule, _) in lucky_metadata.cron_jobs {
+            for warning in super::cron::schedule_warnings(&schedule) {
+                log::warn!("Cron schedule \"{}\": {}", schedule, warning);
+            }
             schedule
                 .parse::<cron::Schedule>()
                 .map_err(|_| format_err!("Could not parse cron schedule: {}", schedule))?;
@@ -0,0 +1,225 @@
+use anyhow::Context;
+use chrono::{DateTime, Local, Utc};
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::cli::*;
+use crate::config::load_yaml;
+use crate::types::LuckyMetadata;
+
+pub(super) struct CronSubcommand;
+
+impl<'a> CliCommand<'a> for CronSubcommand {
+    fn get_name(&self) -> &'static str {
+        "cron"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Preview and validate the charm's cron job schedules")
+            .long_about(concat!(
+                "Preview and validate the charm's cron job schedules. The next fire times for ",
+                "every cron job in the lucky.yaml file will be printed in both local time and UTC ",
+                "along with warnings for any schedules that look suspicious."
+            ))
+            .arg(Arg::with_name("count")
+                .help("The number of upcoming fire times to print for each cron job")
+                .long("count")
+                .short('n')
+                .takes_value(true)
+                .value_name("N")
+                .default_value("5"))
+            .arg(Arg::with_name("charm_dir")
+                .help("The path to the charm you want to check")
+                .required(false)
+                .default_value("."))
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_charm_cron",
+            content: include_str!("cron/cron.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        // Get charm dir
+        let charm_path = Path::new(
+            args.value_of("charm_dir")
+                .expect("Missing required argument: charm_dir"),
+        );
+
+        // Get the number of fire times to show
+        let count: usize = args
+            .value_of("count")
+            .expect("Missing required argument: count")
+            .parse()
+            .context("Could not parse count as a positive integer")?;
+
+        // Load lucky metadata
+        let lucky_metadata = load_yaml::<LuckyMetadata>(&charm_path, "lucky")?;
+
+        if lucky_metadata.cron_jobs.is_empty() {
+            writeln!(std::io::stdout(), "No cron jobs defined in lucky.yaml")?;
+            return Ok(data);
+        }
+
+        let mut has_errors = false;
+        let mut stdout = std::io::stdout();
+        for schedule_str in lucky_metadata.cron_jobs.keys() {
+            writeln!(stdout, "{}", schedule_str)?;
+
+            // Print any warnings for the schedule
+            for warning in schedule_warnings(schedule_str) {
+                writeln!(stdout, "  Warning: {}", warning)?;
+            }
+
+            // Parse the schedule
+            let schedule: cron::Schedule = match schedule_str.parse() {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    writeln!(stdout, "  Error: Could not parse cron schedule: {}", e)?;
+                    writeln!(stdout)?;
+                    has_errors = true;
+                    continue;
+                }
+            };
+
+            // Print the upcoming fire times
+            let upcoming = fire_times_after(&schedule, &Utc::now(), count);
+            if upcoming.is_empty() {
+                writeln!(stdout, "  Warning: This schedule will never fire")?;
+            }
+            for date in upcoming {
+                let local_date: DateTime<Local> = date.with_timezone(&Local);
+                writeln!(
+                    stdout,
+                    "  {}  ( {} )",
+                    local_date.format("%Y-%m-%d %H:%M:%S %:z"),
+                    date.format("%Y-%m-%d %H:%M:%S UTC")
+                )?;
+            }
+
+            writeln!(stdout)?;
+        }
+
+        // Exit non-zero if any of the schedules could not be parsed
+        if has_errors {
+            return Err(CliError::Exit(1).into());
+        }
+
+        Ok(data)
+    }
+}
+
+/// Get the next `count` times that the schedule fires after the given time
+pub(super) fn fire_times_after(
+    schedule: &cron::Schedule,
+    after: &DateTime<Utc>,
+    count: usize,
+) -> Vec<DateTime<Utc>> {
+    schedule.after(after).take(count).collect()
+}
+
+/// Get a list of warnings for common mistakes made when writing cron schedules
+///
+/// Lucky uses cron schedules with a leading seconds field and an optional trailing year field
+/// instead of the five-field format used by the system crontab.
+pub(super) fn schedule_warnings(schedule_str: &str) -> Vec<String> {
+    let mut warnings = vec![];
+    let fields: Vec<&str> = schedule_str.split_whitespace().collect();
+
+    // Check for the number of fields
+    match fields.len() {
+        5 => warnings.push(
+            concat!(
+                "Schedule has five fields, which looks like a standard crontab schedule. Lucky ",
+                "schedules start with a seconds field: try prefixing the schedule with `0 `."
+            )
+            .into(),
+        ),
+        6 | 7 => (),
+        n => warnings.push(format!(
+            "Schedule has {} fields, but Lucky schedules must have 6 or 7 fields",
+            n
+        )),
+    }
+
+    // Check for a schedule that fires every second
+    if fields.len() >= 6 && (fields[0] == "*" || fields[0] == "*/1") {
+        warnings.push(
+            concat!(
+                "Schedule fires every second. If you meant to run the job every minute, set the ",
+                "seconds field to `0`."
+            )
+            .into(),
+        );
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn fire_times_are_in_order_after_the_given_time() {
+        let schedule: cron::Schedule = "0 30 9 * * Mon".parse().unwrap();
+        // A Wednesday
+        let after = Utc.ymd(2020, 3, 4).and_hms(12, 0, 0);
+
+        assert_eq!(
+            fire_times_after(&schedule, &after, 2),
+            vec![
+                Utc.ymd(2020, 3, 9).and_hms(9, 30, 0),
+                Utc.ymd(2020, 3, 16).and_hms(9, 30, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn schedule_in_the_past_never_fires() {
+        let schedule: cron::Schedule = "0 0 0 1 1 * 2000".parse().unwrap();
+        let after = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+
+        assert!(fire_times_after(&schedule, &after, 5).is_empty());
+    }
+
+    #[test]
+    fn warns_about_crontab_schedules() {
+        let warnings = schedule_warnings("30 9 * * Mon");
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("five fields"));
+    }
+
+    #[test]
+    fn warns_about_schedules_that_fire_every_second() {
+        assert_eq!(schedule_warnings("* 30 9 * * Mon").len(), 1);
+        assert_eq!(schedule_warnings("*/1 * * * * *").len(), 1);
+    }
+
+    #[test]
+    fn valid_schedules_have_no_warnings() {
+        assert!(schedule_warnings("0 30 9 * * Mon").is_empty());
+        assert!(schedule_warnings("0 0 0 1 1 * 2030").is_empty());
+    }
+
+    #[test]
+    fn warns_about_wrong_field_count() {
+        let warnings = schedule_warnings("0 0");
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("2 fields"));
+    }
+}
@@ -14,6 +14,7 @@ mod public_address;
 mod random;
 mod relation;
 mod set_status;
+mod template;
 
 #[cfg(feature = "daemon")]
 use crate::cli::daemon::get_daemon_client;
@@ -53,6 +54,7 @@ impl<'a> CliCommand<'a> for ClientSubcommand {
             Box::new(leader::LeaderSubcommand),
             Box::new(random::RandomSubcommand),
             Box::new(get_resource::GetResourceSubcommand),
+            Box::new(template::TemplateSubcommand),
         ]
     }
 
@@ -3,13 +3,34 @@ use clap::{App, Arg, ArgMatches};
 use crate::cli::*;
 
 mod apply_updates;
+mod capabilities;
+mod cp;
 mod delete;
+mod depends_on;
+mod device;
+mod dns;
 mod env;
+mod exec;
+mod extra_host;
+mod healthcheck;
 mod image;
+mod label;
+mod logs;
+mod network;
 mod port;
+mod registry_auth;
+mod resources;
+mod security_opt;
 mod set_command;
 mod set_entrypoint;
+mod set_hostname;
 mod set_network;
+mod set_privileged;
+mod set_restart_policy;
+mod set_stop_signal;
+mod set_stop_timeout;
+mod set_update_strategy;
+mod status;
 mod volume;
 
 pub(super) struct ContainerSubcommand;
@@ -37,6 +58,27 @@ impl<'a> CliCommand<'a> for ContainerSubcommand {
             Box::new(delete::DeleteSubcommand),
             Box::new(port::PortSubcommand),
             Box::new(set_network::SetNetworkSubcommand),
+            Box::new(set_restart_policy::SetRestartPolicySubcommand),
+            Box::new(set_update_strategy::SetUpdateStrategySubcommand),
+            Box::new(set_stop_signal::SetStopSignalSubcommand),
+            Box::new(set_stop_timeout::SetStopTimeoutSubcommand),
+            Box::new(healthcheck::HealthcheckSubcommand),
+            Box::new(resources::ResourcesSubcommand),
+            Box::new(set_hostname::SetHostnameSubcommand),
+            Box::new(label::LabelSubcommand),
+            Box::new(extra_host::ExtraHostSubcommand),
+            Box::new(dns::DnsSubcommand),
+            Box::new(set_privileged::SetPrivilegedSubcommand),
+            Box::new(capabilities::CapabilitiesSubcommand),
+            Box::new(device::DeviceSubcommand),
+            Box::new(security_opt::SecurityOptSubcommand),
+            Box::new(registry_auth::RegistryAuthSubcommand),
+            Box::new(logs::LogsSubcommand),
+            Box::new(exec::ExecSubcommand),
+            Box::new(cp::CpSubcommand),
+            Box::new(network::NetworkSubcommand),
+            Box::new(depends_on::DependsOnSubcommand),
+            Box::new(status::StatusSubcommand),
         ]
     }
 
@@ -0,0 +1,153 @@
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct CapabilitiesSubcommand;
+
+impl<'a> CliCommand<'a> for CapabilitiesSubcommand {
+    fn get_name(&self) -> &'static str {
+        "capabilities"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Get and set the Linux capabilities of the container")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_capabilities",
+            content: include_str!("cli_help/capabilities.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Set the capabilities to add and drop")
+            .long_about(concat!(
+                "Set the capabilities to add and drop. Any existing settings will be replaced. ",
+                "Running with no capabilities will reset the container to the Docker defaults."
+            ))
+            .arg(Arg::with_name("add")
+                .help("A capability to add, such as `NET_ADMIN`. May be passed multiple times.")
+                .long("add")
+                .short('a')
+                .takes_value(true)
+                .value_name("capability")
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("drop")
+                .help("A capability to drop, or `ALL`. May be passed multiple times.")
+                .long("drop")
+                .short('d')
+                .takes_value(true)
+                .value_name("capability")
+                .multiple(true)
+                .number_of_values(1))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let add = args
+            .values_of("add")
+            .map_or_else(Vec::new, |x| x.map(|c| c.to_uppercase()).collect());
+        let drop = args
+            .values_of("drop")
+            .map_or_else(Vec::new, |x| x.map(|c| c.to_uppercase()).collect());
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        client
+            .container_capabilities_set(add, drop, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Get the capabilities added to and dropped from the container")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        let response = client
+            .container_capabilities_get(container.map(Into::into))
+            .call()?;
+
+        for capability in response.add {
+            writeln!(std::io::stdout(), "add {}", capability)?;
+        }
+        for capability in response.drop {
+            writeln!(std::io::stdout(), "drop {}", capability)?;
+        }
+
+        Ok(data)
+    }
+}
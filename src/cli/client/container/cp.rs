@@ -0,0 +1,119 @@
+use anyhow::{bail, Context};
+use clap::{App, Arg, ArgMatches};
+
+use std::path::{Path, PathBuf};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct CpSubcommand;
+
+impl<'a> CliCommand<'a> for CpSubcommand {
+    fn get_name(&self) -> &'static str {
+        "cp"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Copy files and directories between the host and the container")
+            .long_about(concat!(
+                "Copy files and directories between the host and the container. Paths in the ",
+                "container are prefixed with a `:`. Permissions and ownership are preserved."
+            ))
+            .arg(Arg::with_name("source")
+                .help("The path to copy from. Prefix the path with `:` for a path in the container.")
+                .required(true))
+            .arg(Arg::with_name("destination")
+                .help("The path to copy to. Prefix the path with `:` for a path in the container.")
+                .required(true))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_cp",
+            content: include_str!("cli_help/cp.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let source = args
+            .value_of("source")
+            .expect("Missing required argument: source");
+        let destination = args
+            .value_of("destination")
+            .expect("Missing required argument: destination");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Host paths are read and written by the daemon, which runs on the host, so they would refer
+        // to the host's files instead of the ones the user meant if the client is running inside of
+        // a container
+        if running_in_container() {
+            bail!(
+                "`lucky container cp` copies to and from the host's filesystem so it has to be run \
+                on the host, not inside of a container"
+            );
+        }
+
+        match (source.starts_with(':'), destination.starts_with(':')) {
+            // Copy from the host into the container
+            (false, true) => {
+                client
+                    .container_copy_to(
+                        absolute_host_path(source)?,
+                        destination[1..].into(),
+                        container.map(Into::into),
+                    )
+                    .call()?;
+            }
+            // Copy from the container to the host
+            (true, false) => {
+                client
+                    .container_copy_from(
+                        source[1..].into(),
+                        absolute_host_path(destination)?,
+                        container.map(Into::into),
+                    )
+                    .call()?;
+            }
+            _ => bail!(
+                "Exactly one of the source and destination must be a container path starting \
+                with `:`"
+            ),
+        }
+
+        Ok(data)
+    }
+}
+
+/// Get the absolute path to a host path relative to the current directory. The daemon doesn't run
+/// in the same directory as the client so relative paths have to be resolved by the client.
+fn absolute_host_path(path: &str) -> anyhow::Result<String> {
+    let path = PathBuf::from(path);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .context("Could not get current directory")?
+            .join(path)
+    };
+
+    Ok(path.to_string_lossy().into())
+}
+
+/// Returns `true` if this process is running inside of a Docker or Podman container
+fn running_in_container() -> bool {
+    Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists()
+}
@@ -0,0 +1,140 @@
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct DependsOnSubcommand;
+
+impl<'a> CliCommand<'a> for DependsOnSubcommand {
+    fn get_name(&self) -> &'static str {
+        "depends-on"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Get and set the containers that the container depends on")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_depends-on",
+            content: include_str!("cli_help/depends_on.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Set the containers that the container depends on")
+            .long_about(concat!(
+                "Set the named containers that the container depends on. The container will be ",
+                "started after the containers that it depends on. Any existing dependencies will ",
+                "be replaced. Running with no dependencies will remove the dependencies."
+            ))
+            .arg(Arg::with_name("dependencies")
+                .help("The dependencies in the format `container[:condition]`")
+                .long_help(concat!(
+                    "The dependencies in the format `container[:condition]`. The condition may be ",
+                    "`started`, the default, to wait for the dependency to be started or ",
+                    "`healthy` to wait for the dependency's health check to pass."
+                ))
+                .multiple(true))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let dependencies = args
+            .values_of("dependencies")
+            .map_or_else(Vec::new, |x| x.map(Into::into).collect());
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        client
+            .container_depends_on_set(dependencies, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Get the dependencies, one per line, in the format `container:condition`")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        for dependency in client
+            .container_depends_on_get(container.map(Into::into))
+            .call()?
+            .dependencies
+        {
+            writeln!(std::io::stdout(), "{}", dependency)?;
+        }
+
+        Ok(data)
+    }
+}
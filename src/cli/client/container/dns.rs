@@ -0,0 +1,154 @@
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct DnsSubcommand;
+
+impl<'a> CliCommand<'a> for DnsSubcommand {
+    fn get_name(&self) -> &'static str {
+        "dns"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Get and set the container's DNS servers and search domains")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_dns",
+            content: include_str!("cli_help/dns.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Set the DNS servers and search domains")
+            .long_about(concat!(
+                "Set the DNS servers and search domains. Any existing settings will be replaced. ",
+                "Running with no servers or search domains will make the container use the ",
+                "host's DNS settings."
+            ))
+            .arg(Arg::with_name("server")
+                .help("A DNS server for the container to use. May be passed multiple times.")
+                .long("server")
+                .short('s')
+                .takes_value(true)
+                .value_name("ip")
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("search")
+                .help("A DNS search domain for the container to use. May be passed multiple times.")
+                .long("search")
+                .short('S')
+                .takes_value(true)
+                .value_name("domain")
+                .multiple(true)
+                .number_of_values(1))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let servers = args
+            .values_of("server")
+            .map_or_else(Vec::new, |x| x.map(Into::into).collect());
+        let search_domains = args
+            .values_of("search")
+            .map_or_else(Vec::new, |x| x.map(Into::into).collect());
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        client
+            .container_dns_set(servers, search_domains, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Get the DNS servers and search domains")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        let response = client
+            .container_dns_get(container.map(Into::into))
+            .call()?;
+
+        for server in response.servers {
+            writeln!(std::io::stdout(), "server {}", server)?;
+        }
+        for domain in response.search_domains {
+            writeln!(std::io::stdout(), "search {}", domain)?;
+        }
+
+        Ok(data)
+    }
+}
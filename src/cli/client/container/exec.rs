@@ -0,0 +1,101 @@
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use std::io::{Read, Write};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct ExecSubcommand;
+
+impl<'a> CliCommand<'a> for ExecSubcommand {
+    fn get_name(&self) -> &'static str {
+        "exec"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Run a command in the container")
+            .long_about(concat!(
+                "Run a command in the container. The output of the command will be printed as it ",
+                "runs and `lucky container exec` will exit with the exit code of the command."
+            ))
+            .setting(AppSettings::TrailingVarArg)
+            .arg(Arg::with_name("stdin")
+                .help("Forward stdin to the command")
+                .long_help(concat!(
+                    "Forward stdin to the command. Stdin will be read until it is closed before ",
+                    "the command is started."
+                ))
+                .long("stdin")
+                .short('i'))
+            .arg(Arg::with_name("command")
+                .help("The command to run")
+                .multiple(true)
+                .required(true))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_exec",
+            content: include_str!("cli_help/exec.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let command: Vec<String> = args
+            .values_of("command")
+            .expect("Missing required argument: command")
+            .map(Into::into)
+            .collect();
+
+        // Read stdin if it should be forwarded
+        let stdin = if args.is_present("stdin") {
+            let mut stdin = String::new();
+            std::io::stdin().read_to_string(&mut stdin)?;
+            Some(stdin)
+        } else {
+            None
+        };
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Print the command output as it is received
+        let mut exit_code = 0;
+        for response in client
+            .container_exec(command, stdin, container.map(Into::into))
+            .more()?
+        {
+            let response = response?;
+
+            if response.stderr {
+                write!(std::io::stderr(), "{}", response.output)?;
+            } else {
+                write!(std::io::stdout(), "{}", response.output)?;
+                std::io::stdout().flush()?;
+            }
+
+            if let Some(code) = response.exit_code {
+                exit_code = code as i32;
+            }
+        }
+
+        // Exit with the exit code of the command
+        if exit_code != 0 {
+            return Err(CliError::Exit(exit_code).into());
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Context;
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{ContainerHealthcheck, VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct HealthcheckSubcommand;
+
+impl<'a> CliCommand<'a> for HealthcheckSubcommand {
+    fn get_name(&self) -> &'static str {
+        "healthcheck"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .about("Configure the container health check")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(UnsetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_healthcheck",
+            content: include_str!("cli_help/healthcheck.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set the container health check")
+            .arg(Arg::with_name("cmd")
+                .help("The command to run in the container to check its health")
+                .long_help(concat!(
+                    "The command to run in the container to check its health. The command is run ",
+                    "with the container's shell and should exit non-zero if the container is ",
+                    "unhealthy."
+                ))
+                .long("cmd")
+                .takes_value(true)
+                .value_name("command")
+                .required(true))
+            .arg(Arg::with_name("interval")
+                .help("The number of seconds to wait between checks")
+                .long("interval")
+                .short('i')
+                .takes_value(true)
+                .value_name("seconds"))
+            .arg(Arg::with_name("timeout")
+                .help("The number of seconds to wait before considering the check to have hung")
+                .long("timeout")
+                .short('t')
+                .takes_value(true)
+                .value_name("seconds"))
+            .arg(Arg::with_name("retries")
+                .help("The number of consecutive failures needed to report the container unhealthy")
+                .long("retries")
+                .short('r')
+                .takes_value(true)
+                .value_name("count"))
+            .arg(Arg::with_name("start_period")
+                .help("The number of seconds to give the container to start before counting failures")
+                .long("start-period")
+                .short('s')
+                .takes_value(true)
+                .value_name("seconds"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let cmd = args
+            .value_of("cmd")
+            .expect("Missing required argument: cmd");
+
+        // Parse an optional integer argument
+        let parse_arg = |name: &str| -> anyhow::Result<Option<i64>> {
+            args.value_of(name)
+                .map(|x| {
+                    x.parse::<u32>()
+                        .map(Into::into)
+                        .context(format!("Could not parse {} as a positive integer", name))
+                })
+                .transpose()
+        };
+
+        let healthcheck = ContainerHealthcheck {
+            cmd: cmd.into(),
+            interval: parse_arg("interval")?,
+            timeout: parse_arg("timeout")?,
+            retries: parse_arg("retries")?,
+            start_period: parse_arg("start_period")?,
+        };
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Set the health check
+        client
+            .container_healthcheck_set(Some(healthcheck), container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct UnsetSubcommand;
+
+impl<'a> CliCommand<'a> for UnsetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "unset"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Remove the container health check")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Remove the health check
+        client
+            .container_healthcheck_set(None, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Get the container health check command")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Print out the health check command if it is set
+        if let Some(healthcheck) = client
+            .container_healthcheck_get(container.map(Into::into))
+            .call()?
+            .healthcheck
+        {
+            writeln!(std::io::stdout(), "{}", healthcheck.cmd)?;
+        }
+
+        Ok(data)
+    }
+}
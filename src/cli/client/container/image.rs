@@ -19,7 +19,12 @@ impl<'a> CliCommand<'a> for ImageSubcommand {
     }
 
     fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
-        vec![Box::new(GetSubcommand), Box::new(SetSubcommand)]
+        vec![
+            Box::new(GetSubcommand),
+            Box::new(SetSubcommand),
+            Box::new(SetPullPolicySubcommand),
+            Box::new(UpgradeSubcommand),
+        ]
     }
 
     fn get_doc(&self) -> Option<CliDoc> {
@@ -96,10 +101,26 @@ impl<'a> CliCommand<'a> for SetSubcommand {
             .about("Set the container image")
             .arg(Arg::with_name("image")
                 .help("The container image")
-                .required(true))
+                .required_unless("from_resource"))
             .arg(Arg::with_name("no_pull")
                 .help("Don't attempt to pull image before running container")
+                .long_help(concat!(
+                    "Don't attempt to pull image before running container. This is the same as ",
+                    "setting the pull policy to `never`."
+                ))
                 .long("no-pull"))
+            .arg(Arg::with_name("from_resource")
+                .help("Load the image from an image tarball attached as a Juju resource")
+                .long_help(concat!(
+                    "Load the image from an image tarball, such as one created with `docker save`, ",
+                    "attached as a Juju resource. The loaded image reference will be printed and ",
+                    "the image will be reloaded when a new revision of the resource is attached."
+                ))
+                .long("from-resource")
+                .short('r')
+                .takes_value(true)
+                .value_name("resource_name")
+                .conflicts_with_all(&["image", "no_pull"]))
             .arg(super::container_arg())
     }
 
@@ -112,9 +133,6 @@ impl<'a> CliCommand<'a> for SetSubcommand {
     }
 
     fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
-        let image = args
-            .value_of("image")
-            .expect("Missing required argument `image`");
         let container = args.value_of("container");
 
         // Get client connection
@@ -124,6 +142,22 @@ impl<'a> CliCommand<'a> for SetSubcommand {
             .downcast()
             .expect("Invalid type");
 
+        // If the image should be loaded from a resource
+        if let Some(resource_name) = args.value_of("from_resource") {
+            // Load the image and set it for the specified container
+            let response = client
+                .container_image_set_from_resource(resource_name.into(), container.map(Into::into))
+                .call()?;
+
+            writeln!(std::io::stdout(), "{}", response.image)?;
+
+            return Ok(data);
+        }
+
+        let image = args
+            .value_of("image")
+            .expect("Missing required argument `image`");
+
         // Set the image for the specified container
         client
             .container_image_set(
@@ -136,3 +170,126 @@ impl<'a> CliCommand<'a> for SetSubcommand {
         Ok(data)
     }
 }
+
+struct SetPullPolicySubcommand;
+
+impl<'a> CliCommand<'a> for SetPullPolicySubcommand {
+    fn get_name(&self) -> &'static str {
+        "set-pull-policy"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set when the container image is pulled")
+            .arg(Arg::with_name("policy")
+                .help("The pull policy")
+                .long_help(concat!(
+                    "The pull policy. `always` pulls the image every time the container is ",
+                    "created, `if-not-present` only pulls the image if it isn't already on the ",
+                    "host, and `never` doesn't pull the image. The default policy is `always`."
+                ))
+                .possible_values(&["always", "if-not-present", "never"])
+                .required(true))
+            .arg(Arg::with_name("check_updates")
+                .help("Periodically check the registry for a newer version of the image")
+                .long_help(concat!(
+                    "Periodically check the registry for a newer version of the image and ",
+                    "re-create the container when there is one"
+                ))
+                .long("check-updates")
+                .short('u'))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let policy = args
+            .value_of("policy")
+            .expect("Missing required argument: policy");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Set the pull policy for the specified container
+        client
+            .container_image_set_pull_policy(
+                policy.into(),
+                args.is_present("check_updates"),
+                container.map(Into::into),
+            )
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct UpgradeSubcommand;
+
+impl<'a> CliCommand<'a> for UpgradeSubcommand {
+    fn get_name(&self) -> &'static str {
+        "upgrade"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Pull the latest version of the image and re-create the container if needed")
+            .long_about(concat!(
+                "Pull the latest version of the container image and re-create the container if it ",
+                "is running an older version. Prints `upgraded` if the container was re-created ",
+                "and `up-to-date` if it wasn't."
+            ))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Upgrade the image for the specified container
+        let response = client
+            .container_image_upgrade(container.map(Into::into))
+            .call()?;
+
+        // Write response
+        writeln!(
+            std::io::stdout(),
+            "{}",
+            if response.upgraded {
+                "upgraded"
+            } else {
+                "up-to-date"
+            }
+        )?;
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::format_err;
+use clap::{App, Arg, ArgMatches};
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct LabelSubcommand;
+
+impl<'a> CliCommand<'a> for LabelSubcommand {
+    fn get_name(&self) -> &'static str {
+        "label"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Get and set container labels")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(GetSubcommand),
+            Box::new(SetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_label",
+            content: include_str!("cli_help/label.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Get a container label")
+            .long_about(concat!(
+                "Get a label from the container. ",
+                "If you leave `key` unspecified, all labels will be printed out, ",
+                "one per line, in the format `key=value`."
+            ))
+            .arg(Arg::with_name("key")
+                .help("The label to get"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let key = args.value_of("key");
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        let labels = client
+            .container_label_get_all(container.map(Into::into))
+            .call()?
+            .labels;
+
+        // If a specific key was given
+        if let Some(key) = key {
+            // Print out the requested value
+            writeln!(
+                std::io::stdout(),
+                "{}",
+                labels.get(key).map(AsRef::as_ref).unwrap_or("")
+            )?;
+
+        // If no key was given
+        } else {
+            // Print all of the labels
+            for (key, value) in labels {
+                writeln!(std::io::stdout(), "{}={}", key, value)?;
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set container labels")
+            .arg(Arg::with_name("labels")
+                .help("The labels to set on the container as `key=value` pairs separated by spaces")
+                .long_help("The labels to set on the container as `key=value` pairs separated by \
+                            spaces. Setting values to nothing will remove the label.")
+                .required(true)
+                .multiple(true))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let raw_labels = args.values_of("labels").expect("Missing required arg: labels");
+
+        // Parse key-value pairs. Label keys are conventionally namespaced with dots, such as
+        // `com.example.key`, which the key-value pairs used for env vars don't allow.
+        let mut labels = HashMap::new();
+        for raw_label in raw_labels {
+            let mut parts = raw_label.splitn(2, '=');
+            let key = parts.next().unwrap_or("");
+            let value = parts
+                .next()
+                .ok_or_else(|| format_err!("Could not parse key-value pair: {}", raw_label))?;
+
+            if key.is_empty() {
+                return Err(format_err!("Label key cannot be empty: {}", raw_label));
+            }
+
+            labels.insert(
+                key.to_string(),
+                if value == "" {
+                    None
+                } else {
+                    Some(value.to_string())
+                },
+            );
+        }
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Set the labels. If a value was not provided the label will be deleted.
+        client
+            .container_label_set(labels, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{format_err, Context};
+use chrono::{DateTime, Utc};
+use clap::{App, Arg, ArgMatches};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+lazy_static! {
+    /// The regular expression for a relative time such as `10m`
+    static ref RELATIVE_TIME_REGEX: Regex = Regex::new(r"^(?P<number>[0-9]+)(?P<unit>[smhd])$")
+        .expect("Could not compile regex");
+}
+
+pub(super) struct LogsSubcommand;
+
+impl<'a> CliCommand<'a> for LogsSubcommand {
+    fn get_name(&self) -> &'static str {
+        "logs"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Get the container logs")
+            .arg(Arg::with_name("tail")
+                .help("The number of lines to show from the end of the logs")
+                .long("tail")
+                .short('n')
+                .takes_value(true)
+                .value_name("N"))
+            .arg(Arg::with_name("since")
+                .help("Only show logs since the given time")
+                .long_help(concat!(
+                    "Only show logs since the given time. The time may be a relative time such ",
+                    "as `30s`, `10m`, `2h`, or `1d`, an RFC 3339 timestamp such as ",
+                    "`2020-01-01T12:00:00Z`, or a Unix timestamp."
+                ))
+                .long("since")
+                .short('s')
+                .takes_value(true)
+                .value_name("time"))
+            .arg(Arg::with_name("follow")
+                .help("Keep printing new log output until the container stops")
+                .long("follow")
+                .short('f'))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_logs",
+            content: include_str!("cli_help/logs.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let follow = args.is_present("follow");
+        let tail = args
+            .value_of("tail")
+            .map(|x| {
+                x.parse::<u32>()
+                    .map(Into::into)
+                    .context("Could not parse tail as a positive integer")
+            })
+            .transpose()?;
+        let since = args.value_of("since").map(parse_since).transpose()?;
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Print the log output as it is received
+        for response in client
+            .container_logs(tail, since, follow, container.map(Into::into))
+            .more()?
+        {
+            let response = response?;
+
+            if response.stderr {
+                write!(std::io::stderr(), "{}", response.output)?;
+            } else {
+                write!(std::io::stdout(), "{}", response.output)?;
+                std::io::stdout().flush()?;
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Parse a time to show logs since into a Unix timestamp
+fn parse_since(since: &str) -> anyhow::Result<i64> {
+    // Parse a relative time
+    if let Some(captures) = RELATIVE_TIME_REGEX.captures(since) {
+        let number: i64 = captures
+            .name("number")
+            .expect("Expected number")
+            .as_str()
+            .parse()
+            .context(format!("Relative time is too large: {}", since))?;
+        let seconds = match captures.name("unit").expect("Expected unit").as_str() {
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => 1,
+        };
+
+        Ok(Utc::now().timestamp() - number * seconds)
+
+    // Parse a Unix timestamp
+    } else if let Ok(timestamp) = since.parse::<i64>() {
+        Ok(timestamp)
+
+    // Parse an RFC 3339 timestamp
+    } else {
+        DateTime::parse_from_rfc3339(since)
+            .map(|x| x.timestamp())
+            .map_err(|_| format_err!("Could not parse time: {}", since))
+    }
+}
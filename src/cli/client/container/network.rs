@@ -0,0 +1,222 @@
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct NetworkSubcommand;
+
+impl<'a> CliCommand<'a> for NetworkSubcommand {
+    fn get_name(&self) -> &'static str {
+        "network"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Connect the container to networks managed by Lucky")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(JoinSubcommand),
+            Box::new(LeaveSubcommand),
+            Box::new(ListSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_network",
+            content: include_str!("cli_help/network.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct JoinSubcommand;
+
+impl<'a> CliCommand<'a> for JoinSubcommand {
+    fn get_name(&self) -> &'static str {
+        "join"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Connect the container to a managed network")
+            .long_about(concat!(
+                "Connect the container to a managed network. The network will be created if it ",
+                "doesn't exist yet."
+            ))
+            .arg(Arg::with_name("network_name")
+                .help("The name of the network")
+                .required(true))
+            .arg(Arg::with_name("scope")
+                .help("Which units share the network")
+                .long_help(concat!(
+                    "Which units share the network. `unit` networks are only used by the ",
+                    "containers of this unit and `charm` networks are shared by every unit of the ",
+                    "charm on the same machine."
+                ))
+                .long("scope")
+                .short('s')
+                .possible_values(&["unit", "charm"])
+                .default_value("unit"))
+            .arg(Arg::with_name("alias")
+                .help("An extra DNS name that the container can be reached at on the network")
+                .long("alias")
+                .short('a')
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let network_name = args
+            .value_of("network_name")
+            .expect("Missing required argument: network_name");
+        let scope = args
+            .value_of("scope")
+            .expect("Missing required argument: scope");
+        let aliases = args
+            .values_of("alias")
+            .map(|values| values.map(Into::into).collect())
+            .unwrap_or_default();
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Join the network
+        client
+            .container_network_join(
+                network_name.into(),
+                scope.into(),
+                aliases,
+                container.map(Into::into),
+            )
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct LeaveSubcommand;
+
+impl<'a> CliCommand<'a> for LeaveSubcommand {
+    fn get_name(&self) -> &'static str {
+        "leave"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Disconnect the container from a managed network")
+            .arg(Arg::with_name("network_name")
+                .help("The name of the network")
+                .required(true))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let network_name = args
+            .value_of("network_name")
+            .expect("Missing required argument: network_name");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Leave the network
+        client
+            .container_network_leave(network_name.into(), container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct ListSubcommand;
+
+impl<'a> CliCommand<'a> for ListSubcommand {
+    fn get_name(&self) -> &'static str {
+        "list"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("List the managed networks that the container is connected to")
+            .long_about(concat!(
+                "List the managed networks that the container is connected to. Each network is ",
+                "printed on its own line in the format `name scope alias1,alias2`."
+            ))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        for network in client
+            .container_network_get_all(container.map(Into::into))
+            .call()?
+            .networks
+        {
+            writeln!(
+                std::io::stdout(),
+                "{} {} {}",
+                network.network_name,
+                network.scope,
+                network.aliases.join(",")
+            )?;
+        }
+
+        Ok(data)
+    }
+}
@@ -1,11 +1,8 @@
-use anyhow::Context;
 use clap::{App, Arg, ArgMatches};
 
 use std::io::Write;
 
 use crate::cli::*;
-#[cfg(feature = "daemon")]
-use crate::docker::PortBinding;
 use crate::rpc::{VarlinkClient, VarlinkClientInterface};
 
 pub(super) struct PortSubcommand;
@@ -56,9 +53,21 @@ impl<'a> CliCommand<'a> for AddSubcommand {
             .arg(Arg::with_name("port_binding")
                 .help("The port binding to add in the format: `host_port:container_port/proto`.")
                 .long_help(concat!(
-                    "The port binding to add in the format: `host_port:container_port/proto`. ",
-                    "the `/proto` suffix is optional and defaults to `/tcp`."
+                    "The port binding to add in the format: ",
+                    "`[host_ip:]host_port[-end]:container_port[-end][/proto]`. The host IP is ",
+                    "optional and defaults to binding all of the host's IPs. A range of ports ",
+                    "can be bound with `start-end` where the host and container ranges are the ",
+                    "same size. The `/proto` suffix is optional and may be `/tcp`, `/udp`, or ",
+                    "`/sctp`. It defaults to `/tcp`."
                 )))
+            .arg(Arg::with_name("expose")
+                .help("Open the host ports in Juju")
+                .long_help(concat!(
+                    "Open the host ports in Juju. The ports will be closed automatically when the ",
+                    "port binding or the container is removed."
+                ))
+                .long("expose")
+                .short('E'))
             .arg(super::container_arg())
     }
 
@@ -70,12 +79,6 @@ impl<'a> CliCommand<'a> for AddSubcommand {
         None
     }
 
-    #[cfg(not(feature = "daemon"))]
-    fn execute_command(&self, _args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
-        Ok(data)
-    }
-
-    #[cfg(feature = "daemon")]
     fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
         let container = args.value_of("container");
 
@@ -89,17 +92,10 @@ impl<'a> CliCommand<'a> for AddSubcommand {
         let port_binding = args
             .value_of("port_binding")
             .expect("Missing required argument: port");
-        let port_binding = port_binding
-            .parse::<PortBinding>()
-            .context("Could not parse port binding")?;
+        let expose = args.is_present("expose");
 
         client
-            .container_port_add(
-                port_binding.host_port.into(),
-                port_binding.container_port.into(),
-                port_binding.protocol,
-                container.map(Into::into),
-            )
+            .container_port_add(port_binding.into(), expose, container.map(Into::into))
             .call()?;
 
         Ok(data)
@@ -120,7 +116,7 @@ impl<'a> CliCommand<'a> for RemoveSubcommand {
             .arg(Arg::with_name("port_binding")
                 .help("The port binding to remove in the format: `host_port:container_port/proto`.")
                 .long_help(concat!(
-                    "The port binding to remove in the format: `host_port:container_port/proto`. ",
+                    "The port binding to remove in the same format that it was added with. ",
                     "the `/proto` suffix is optional and defaults to `/tcp`."
                 ))
                 .required_unless("all"))
@@ -139,12 +135,6 @@ impl<'a> CliCommand<'a> for RemoveSubcommand {
         None
     }
 
-    #[cfg(not(feature = "daemon"))]
-    fn execute_command(&self, _args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
-        Ok(data)
-    }
-
-    #[cfg(feature = "daemon")]
     fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
         let remove_all = args.is_present("all");
         let container = args.value_of("container");
@@ -164,17 +154,9 @@ impl<'a> CliCommand<'a> for RemoveSubcommand {
             let port_binding = args
                 .value_of("port_binding")
                 .expect("Missing required argument: port");
-            let port_binding = port_binding
-                .parse::<PortBinding>()
-                .context("Could not parse port binding")?;
 
             client
-                .container_port_remove(
-                    port_binding.host_port.into(),
-                    port_binding.container_port.into(),
-                    port_binding.protocol,
-                    container.map(Into::into),
-                )
+                .container_port_remove(port_binding.into(), container.map(Into::into))
                 .call()?;
         }
 
@@ -204,12 +186,6 @@ impl<'a> CliCommand<'a> for ListSubcommand {
         None
     }
 
-    #[cfg(not(feature = "daemon"))]
-    fn execute_command(&self, _args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
-        Ok(data)
-    }
-
-    #[cfg(feature = "daemon")]
     fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
         let container = args.value_of("container");
 
@@ -225,13 +201,7 @@ impl<'a> CliCommand<'a> for ListSubcommand {
             .call()?
             .ports
         {
-            writeln!(
-                std::io::stdout(),
-                "{}:{}/{}",
-                port_binding.host_port,
-                port_binding.container_port,
-                port_binding.protocol
-            )?;
+            writeln!(std::io::stdout(), "{}", port_binding)?;
         }
 
         Ok(data)
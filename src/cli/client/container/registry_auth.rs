@@ -0,0 +1,204 @@
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{ContainerRegistryAuth, VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct RegistryAuthSubcommand;
+
+impl<'a> CliCommand<'a> for RegistryAuthSubcommand {
+    fn get_name(&self) -> &'static str {
+        "registry-auth"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .about("Configure the credentials used to pull the image from a private registry")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(UnsetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_registry-auth",
+            content: include_str!("cli_help/registry_auth.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set where to get the registry credentials from")
+            .arg(Arg::with_name("username")
+                .help("Where to get the username: `config:<key>` or `kv:<key>`")
+                .long("username")
+                .short('u')
+                .takes_value(true)
+                .value_name("source")
+                .required(true))
+            .arg(Arg::with_name("password")
+                .help("Where to get the password: `config:<key>` or `kv:<key>`")
+                .long("password")
+                .short('p')
+                .takes_value(true)
+                .value_name("source")
+                .required(true))
+            .arg(Arg::with_name("server")
+                .help("The registry server address. Defaults to Docker Hub.")
+                .long("server")
+                .short('s')
+                .takes_value(true)
+                .value_name("address"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let auth = ContainerRegistryAuth {
+            server: args.value_of("server").map(Into::into),
+            username: args
+                .value_of("username")
+                .expect("Missing required argument: username")
+                .into(),
+            password: args
+                .value_of("password")
+                .expect("Missing required argument: password")
+                .into(),
+        };
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Set the registry credentials
+        client
+            .container_registry_auth_set(Some(auth), container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct UnsetSubcommand;
+
+impl<'a> CliCommand<'a> for UnsetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "unset"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Remove the registry credentials")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Remove the registry credentials
+        client
+            .container_registry_auth_set(None, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Get where the registry credentials come from")
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Print out the credential sources if they are set
+        if let Some(auth) = client
+            .container_registry_auth_get(container.map(Into::into))
+            .call()?
+            .auth
+        {
+            if let Some(server) = auth.server {
+                writeln!(std::io::stdout(), "server: {}", server)?;
+            }
+            writeln!(std::io::stdout(), "username: {}", auth.username)?;
+            writeln!(std::io::stdout(), "password: {}", auth.password)?;
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::Context;
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+use crate::types::parse_byte_size;
+
+/// The names of the resource limits
+const LIMIT_NAMES: &[&str] = &[
+    "memory",
+    "memory-swap",
+    "cpu-shares",
+    "cpu-quota",
+    "cpu-period",
+    "pids-limit",
+    "shm-size",
+];
+
+/// The resource limits that can be set, whether or not they are byte sizes, and their help
+const LIMITS: &[(&str, bool, &str)] = &[
+    ("memory", true, "The memory limit, such as `512m` or `2g`"),
+    (
+        "memory-swap",
+        true,
+        "The total memory plus swap limit. `-1` allows unlimited swap",
+    ),
+    (
+        "cpu-shares",
+        false,
+        "The relative CPU weight of the container compared to other containers",
+    ),
+    (
+        "cpu-quota",
+        false,
+        "The microseconds of CPU time that the container can use every CPU period",
+    ),
+    ("cpu-period", false, "The length of the CPU period in microseconds"),
+    (
+        "pids-limit",
+        false,
+        "The maximum number of processes in the container. `-1` is unlimited",
+    ),
+    ("shm-size", true, "The size of `/dev/shm`, such as `64m`"),
+];
+
+pub(super) struct ResourcesSubcommand;
+
+impl<'a> CliCommand<'a> for ResourcesSubcommand {
+    fn get_name(&self) -> &'static str {
+        "resources"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .about("Configure container resource limits")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![
+            Box::new(SetSubcommand),
+            Box::new(UnsetSubcommand),
+            Box::new(GetSubcommand),
+        ]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_resources",
+            content: include_str!("cli_help/resources.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct SetSubcommand;
+
+impl<'a> CliCommand<'a> for SetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        let mut app = self.get_base_app()
+            .about("Set container resource limits")
+            .long_about(concat!(
+                "Set container resource limits. Only the limits that are specified will be ",
+                "changed."
+            ));
+
+        // Add an argument for each limit
+        for &(name, _, help) in LIMITS {
+            app = app.arg(Arg::with_name(name)
+                .help(help)
+                .long(name)
+                .takes_value(true)
+                .allow_hyphen_values(true));
+        }
+
+        app.arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Collect the specified limits
+        let mut limits = HashMap::new();
+        for &(name, is_byte_size, _) in LIMITS {
+            if let Some(value) = args.value_of(name) {
+                let value = if is_byte_size && value != "-1" {
+                    parse_byte_size(value)?
+                } else {
+                    value
+                        .parse()
+                        .context(format!("Could not parse {} as an integer", name))?
+                };
+
+                limits.insert(name.to_string(), Some(value));
+            }
+        }
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Set the resource limits
+        client
+            .container_resources_set(limits, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct UnsetSubcommand;
+
+impl<'a> CliCommand<'a> for UnsetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "unset"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Remove container resource limits")
+            .arg(Arg::with_name("limits")
+                .help("The names of the limits to remove")
+                .possible_values(LIMIT_NAMES)
+                .multiple(true)
+                .required_unless("all"))
+            .arg(Arg::with_name("all")
+                .help("Remove all of the container's resource limits")
+                .long("all")
+                .short('A'))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Collect the limits to remove
+        let limits: HashMap<String, Option<i64>> = if args.is_present("all") {
+            LIMIT_NAMES.iter().map(|&x| (x.to_string(), None)).collect()
+        } else {
+            args.values_of("limits")
+                .expect("Missing required argument: limits")
+                .map(|x| (x.to_string(), None))
+                .collect()
+        };
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Remove the resource limits
+        client
+            .container_resources_set(limits, container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
+
+struct GetSubcommand;
+
+impl<'a> CliCommand<'a> for GetSubcommand {
+    fn get_name(&self) -> &'static str {
+        "get"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Get the container resource limits")
+            .long_about(concat!(
+                "Get the container resource limits. If `limit` is not specified, all of the ",
+                "limits that have been set will be printed, one per line, in the format ",
+                "`name=value`."
+            ))
+            .arg(Arg::with_name("limit")
+                .help("The name of the limit to get")
+                .possible_values(LIMIT_NAMES))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let limit = args.value_of("limit");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        let limits = client
+            .container_resources_get(container.map(Into::into))
+            .call()?
+            .limits;
+
+        // If a limit was specified
+        if let Some(limit) = limit {
+            // Print the limit value if it is set
+            if let Some(value) = limits.get(limit) {
+                writeln!(std::io::stdout(), "{}", value)?;
+            }
+
+        // If no limit was specified
+        } else {
+            // Print all limits in a consistent order
+            for &name in LIMIT_NAMES {
+                if let Some(value) = limits.get(name) {
+                    writeln!(std::io::stdout(), "{}={}", name, value)?;
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,71 @@
+use clap::{App, Arg, ArgMatches};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct SetHostnameSubcommand;
+
+impl<'a> CliCommand<'a> for SetHostnameSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set-hostname"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set the container hostname")
+            .arg(Arg::with_name("unset")
+                .help("Unset the hostname instead of setting it")
+                .long_help(concat!(
+                    "Unset the hostname instead of setting it. The container will use the ",
+                    "Docker default hostname, which is the container ID."
+                ))
+                .long("unset")
+                .short('u')
+                .required_unless("hostname"))
+            .arg(Arg::with_name("hostname")
+                .help("The hostname for the container to use")
+                .required_unless("unset"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_set-hostname",
+            content: include_str!("cli_help/set_hostname.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let hostname = args.value_of("hostname");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        if args.is_present("unset") {
+            // Unset the hostname
+            client
+                .container_hostname_set(None, container.map(Into::into))
+                .call()?;
+        } else {
+            // Set the hostname
+            client
+                .container_hostname_set(
+                    Some(hostname.expect("Missing required argument: hostname").into()),
+                    container.map(Into::into),
+                )
+                .call()?;
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,58 @@
+use clap::{App, Arg, ArgMatches};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct SetPrivilegedSubcommand;
+
+impl<'a> CliCommand<'a> for SetPrivilegedSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set-privileged"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(clap::AppSettings::ArgRequiredElseHelp)
+            .about("Run the container in privileged mode")
+            .long_about(concat!(
+                "Run the container in privileged mode. Privileged containers have full access to ",
+                "the host's devices and most of their isolation is disabled. Prefer adding only ",
+                "the capabilities and devices that the container needs with ",
+                "`lucky container capabilities` and `lucky container device`."
+            ))
+            .arg(Arg::with_name("unset")
+                .help("Disable privileged mode instead of enabling it")
+                .long("unset")
+                .short('u'))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_set-privileged",
+            content: include_str!("cli_help/set_privileged.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        client
+            .container_privileged_set(!args.is_present("unset"), container.map(Into::into))
+            .call()?;
+
+        Ok(data)
+    }
+}
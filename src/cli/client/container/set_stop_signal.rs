@@ -0,0 +1,71 @@
+use clap::{App, Arg, ArgMatches};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct SetStopSignalSubcommand;
+
+impl<'a> CliCommand<'a> for SetStopSignalSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set-stop-signal"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set the signal used to stop the container")
+            .arg(Arg::with_name("unset")
+                .help("Unset the stop signal instead of setting it")
+                .long_help(concat!(
+                    "Unset the stop signal instead of setting it. The container will be stopped ",
+                    "with the Docker default signal, `SIGTERM`."
+                ))
+                .long("unset")
+                .short('u')
+                .required_unless("signal"))
+            .arg(Arg::with_name("signal")
+                .help("The signal name or number, such as `SIGINT`, to stop the container with")
+                .required_unless("unset"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_set-stop-signal",
+            content: include_str!("cli_help/set_stop_signal.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let signal = args.value_of("signal");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        if args.is_present("unset") {
+            // Unset the stop signal
+            client
+                .container_set_stop_signal(None, container.map(Into::into))
+                .call()?;
+        } else {
+            // Set the stop signal
+            client
+                .container_set_stop_signal(
+                    Some(signal.expect("Missing required argument: signal").into()),
+                    container.map(Into::into),
+                )
+                .call()?;
+        }
+
+        Ok(data)
+    }
+}
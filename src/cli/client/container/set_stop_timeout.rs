@@ -0,0 +1,73 @@
+use anyhow::Context;
+use clap::{App, Arg, ArgMatches};
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct SetStopTimeoutSubcommand;
+
+impl<'a> CliCommand<'a> for SetStopTimeoutSubcommand {
+    fn get_name(&self) -> &'static str {
+        "set-stop-timeout"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Set how long to wait for the container to stop before killing it")
+            .arg(Arg::with_name("unset")
+                .help("Unset the stop timeout instead of setting it")
+                .long_help(concat!(
+                    "Unset the stop timeout instead of setting it. Lucky will wait the default of ",
+                    "10 seconds for the container to stop."
+                ))
+                .long("unset")
+                .short('u')
+                .required_unless("seconds"))
+            .arg(Arg::with_name("seconds")
+                .help("The number of seconds to wait for the container to stop")
+                .required_unless("unset"))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_set-stop-timeout",
+            content: include_str!("cli_help/set_stop_timeout.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let seconds = args.value_of("seconds");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        if args.is_present("unset") {
+            // Unset the stop timeout
+            client
+                .container_set_stop_timeout(None, container.map(Into::into))
+                .call()?;
+        } else {
+            // Set the stop timeout
+            let seconds: i64 = seconds
+                .expect("Missing required argument: seconds")
+                .parse()
+                .context("Could not parse stop timeout")?;
+            client
+                .container_set_stop_timeout(Some(seconds), container.map(Into::into))
+                .call()?;
+        }
+
+        Ok(data)
+    }
+}
@@ -0,0 +1,94 @@
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use std::io::Write;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+/// The names of the container status fields
+const FIELD_NAMES: &[&str] = &[
+    "state",
+    "exit-code",
+    "restart-count",
+    "started-at",
+    "health",
+    "image-id",
+    "image-digest",
+];
+
+pub(super) struct StatusSubcommand;
+
+impl<'a> CliCommand<'a> for StatusSubcommand {
+    fn get_name(&self) -> &'static str {
+        "status"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .unset_setting(AppSettings::ArgRequiredElseHelp)
+            .about("Get the runtime state of the container")
+            .long_about(concat!(
+                "Get the runtime state of the container. If `field` is not specified, all of the ",
+                "fields that have a value will be printed, one per line, in the format ",
+                "`name=value`."
+            ))
+            .arg(Arg::with_name("field")
+                .help("The name of the status field to get")
+                .possible_values(FIELD_NAMES))
+            .arg(super::container_arg())
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_container_status",
+            content: include_str!("cli_help/status.md"),
+        })
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let container = args.value_of("container");
+        let field = args.value_of("field");
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        let status = client.container_status(container.map(Into::into)).call()?;
+        let fields = vec![
+            ("state", Some(status.state)),
+            ("exit-code", status.exit_code.map(|x| x.to_string())),
+            ("restart-count", status.restart_count.map(|x| x.to_string())),
+            ("started-at", status.started_at),
+            ("health", status.health),
+            ("image-id", status.image_id),
+            ("image-digest", status.image_digest),
+        ];
+
+        // If a field was specified
+        if let Some(field) = field {
+            // Print the field value if it is set
+            if let Some((_, Some(value))) = fields.iter().find(|(name, _)| *name == field) {
+                writeln!(std::io::stdout(), "{}", value)?;
+            }
+
+        // If no field was specified
+        } else {
+            // Print all fields that are set
+            for (name, value) in fields {
+                if let Some(value) = value {
+                    writeln!(std::io::stdout(), "{}={}", name, value)?;
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
@@ -1,9 +1,11 @@
+use anyhow::bail;
 use clap::{App, AppSettings, Arg, ArgMatches};
 
 use std::io::Write;
 
 use crate::cli::*;
 use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+use crate::types::parse_byte_size;
 
 pub(super) struct VolumeSubcommand;
 
@@ -58,11 +60,33 @@ impl<'a> CliCommand<'a> for AddSubcommand {
             .arg(Arg::with_name("source")
                 .help("The source for the volume.")
                 .long_help("The source for the volume: either a name for a named volume or an \
-                            absolute path on the host"
+                            absolute path on the host. Tmpfs mounts don't have a source so only \
+                            the container path is given."
                 ))
             .arg(Arg::with_name("target")
                 .help("The absolute path in the container to mount `source` to")
                 .value_name("container_path"))
+            .arg(Arg::with_name("type")
+                .help("The type of mount")
+                .long_help("The type of mount: `bind` mounts a host path or a Lucky named volume, \
+                            `volume` mounts a Docker managed volume, and `tmpfs` mounts an \
+                            in-memory filesystem")
+                .short('t')
+                .long("type")
+                .possible_values(&["bind", "volume", "tmpfs"])
+                .default_value("bind"))
+            .arg(Arg::with_name("read_only")
+                .help("Mount the volume read-only")
+                .short('r')
+                .long("read-only"))
+            .arg(Arg::with_name("propagation")
+                .help("The mount propagation for bind mounts")
+                .long("propagation")
+                .possible_values(&["private", "rprivate", "shared", "rshared", "slave", "rslave"]))
+            .arg(Arg::with_name("size")
+                .help("The size limit for tmpfs mounts such as `64m`")
+                .long("size")
+                .value_name("size"))
             .arg(super::container_arg())
     }
 
@@ -75,12 +99,19 @@ impl<'a> CliCommand<'a> for AddSubcommand {
     }
 
     fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
-        let source = args
-            .value_of("source")
-            .expect("Missing required argument: source");
-        let target = args
-            .value_of("target")
-            .expect("Missing required argument: target");
+        let mount_type = args.value_of("type").expect("Missing mount type");
+        // Tmpfs mounts don't have a source so the only path is the target
+        let (source, target) = match (args.value_of("source"), args.value_of("target")) {
+            (Some(target), None) if mount_type == "tmpfs" => ("", target),
+            (Some(source), Some(target)) => (source, target),
+            _ => bail!("Missing required argument: container_path"),
+        };
+        let read_only = args.is_present("read_only");
+        let propagation = args.value_of("propagation");
+        let size = match args.value_of("size") {
+            Some(size) => Some(parse_byte_size(size)?),
+            None => None,
+        };
         let container = args.value_of("container");
 
         // Get client connection
@@ -91,7 +122,15 @@ impl<'a> CliCommand<'a> for AddSubcommand {
             .expect("Invalid type");
 
         client
-            .container_volume_add(source.into(), target.into(), container.map(Into::into))
+            .container_volume_add(
+                source.into(),
+                target.into(),
+                Some(mount_type.into()),
+                read_only,
+                propagation.map(Into::into),
+                size,
+                container.map(Into::into),
+            )
             .call()?;
 
         Ok(data)
@@ -165,7 +204,7 @@ impl<'a> CliCommand<'a> for GetSubcommand {
             .long_about(concat!(
                 "If `target` is provided and the volume exists, the source of the volume will be ",
                 "printed. If target is not provided, a list of volumes in the format ",
-                "source:target will be printed."
+                "source:target[:options] will be printed."
             ))
             .arg(Arg::with_name("target")
                 .help("The absolute path, in the container, to the volume you want to get")
@@ -213,7 +252,17 @@ impl<'a> CliCommand<'a> for GetSubcommand {
         } else {
             // Print all of the volumes
             for volume in volumes {
-                writeln!(std::io::stdout(), "{}:{}", volume.source, volume.target)?;
+                if volume.options.is_empty() {
+                    writeln!(std::io::stdout(), "{}:{}", volume.source, volume.target)?;
+                } else {
+                    writeln!(
+                        std::io::stdout(),
+                        "{}:{}:{}",
+                        volume.source,
+                        volume.target,
+                        volume.options.join(",")
+                    )?;
+                }
             }
         }
 
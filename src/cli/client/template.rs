@@ -0,0 +1,116 @@
+use anyhow::Context;
+use clap::{App, Arg, ArgMatches};
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::cli::*;
+use crate::rpc::{VarlinkClient, VarlinkClientInterface};
+
+pub(super) struct TemplateSubcommand;
+
+impl<'a> CliCommand<'a> for TemplateSubcommand {
+    fn get_name(&self) -> &'static str {
+        "template"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .about("Render config file templates")
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![Box::new(RenderSubcommand)]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        Some(CliDoc {
+            name: "lucky_client_template",
+            content: include_str!("cli_help/template.md"),
+        })
+    }
+
+    fn execute_command(&self, _args: &ArgMatches, data: CliData) -> anyhow::Result<CliData> {
+        Ok(data)
+    }
+}
+
+struct RenderSubcommand;
+
+impl<'a> CliCommand<'a> for RenderSubcommand {
+    fn get_name(&self) -> &'static str {
+        "render"
+    }
+
+    #[rustfmt::skip]
+    fn get_app(&self) -> App<'a> {
+        self.get_base_app()
+            .about("Render a template from the charm directory to a file")
+            .long_about(concat!(
+                "Render a Handlebars template from the charm directory to a file. Prints ",
+                "`changed` if the file was changed and `unchanged` if the rendered output was the ",
+                "same as the existing file."
+            ))
+            .arg(Arg::with_name("template")
+                .help("The path to the template, relative to the charm directory")
+                .required(true))
+            .arg(Arg::with_name("output")
+                .help("The path to write the rendered template to")
+                .required(true))
+    }
+
+    fn get_subcommands(&self) -> Vec<Box<dyn CliCommand<'a>>> {
+        vec![]
+    }
+
+    fn get_doc(&self) -> Option<CliDoc> {
+        None
+    }
+
+    fn execute_command(&self, args: &ArgMatches, mut data: CliData) -> anyhow::Result<CliData> {
+        let template = args
+            .value_of("template")
+            .expect("Missing required argument: template");
+        let output = PathBuf::from(
+            args.value_of("output")
+                .expect("Missing required argument: output"),
+        );
+
+        // The daemon doesn't run in the same directory as the client so the output path has to be
+        // resolved by the client
+        let output = if output.is_absolute() {
+            output
+        } else {
+            std::env::current_dir()
+                .context("Could not get current directory")?
+                .join(output)
+        };
+
+        // Get client connection
+        let mut client: Box<VarlinkClient> = data
+            .remove("client")
+            .expect("Missing client data")
+            .downcast()
+            .expect("Invalid type");
+
+        // Render the template
+        let response = client
+            .template_render(template.into(), output.to_string_lossy().into())
+            .call()?;
+
+        // Write response
+        writeln!(
+            std::io::stdout(),
+            "{}",
+            if response.changed {
+                "changed"
+            } else {
+                "unchanged"
+            }
+        )?;
+
+        Ok(data)
+    }
+}
@@ -3,11 +3,13 @@ use anyhow::Context;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use shiplift::Docker;
 
-use crossbeam::{channel::unbounded as unbounded_channel, scope as thread_scope};
+use crossbeam::{
+    channel::{unbounded as unbounded_channel, RecvTimeoutError},
+    scope as thread_scope,
+};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::OpenOptions;
 use std::io::Write;
@@ -17,10 +19,15 @@ use std::sync::{
     Arc, Mutex, RwLock,
 };
 
-use crate::docker::{ContainerInfo, PortBinding, VolumeSource, VolumeTarget};
+use crate::docker::runtime::{new_runtime, ContainerRuntime};
+use crate::docker::{
+    ContainerDependency, ContainerInfo, CredentialSource, DeviceMapping, HealthCheck, MountType,
+    NetworkAttachment, NetworkScope, PortBinding, PullPolicy, RegistryCredentials, RestartPolicy,
+    UpdateStrategy, VolumeMount, VolumeTarget,
+};
 use crate::juju;
 use crate::rpc;
-use crate::types::{LuckyMetadata, ScriptStatus};
+use crate::types::{ContainerDefinition, LuckyMetadata, ScriptStatus};
 
 use crate::VOLUME_DIR;
 
@@ -31,11 +38,13 @@ enum Void {}
 mod tools;
 // Built-in daemon hook handlers
 mod hook_handlers;
+// Config file templating
+mod template;
 // Daemon helper types
 mod types;
 use types::*;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 /// Contains the daemon state, which can be serialize and deserialized for persistance across
 /// daemon crashes, upgrades, etc.
 struct DaemonState {
@@ -51,6 +60,70 @@ struct DaemonState {
     named_containers: HashMap<String, Cd<ContainerInfo>>,
     /// The cached charm config obtained from Juju's `config-get` hook tool
     charm_config: HashMap<String, Cd<JsonValue>>,
+    /// The container definitions from the `lucky.yaml` file as they were when they were last
+    /// applied, with the environment variables interpolated
+    #[serde(default)]
+    declared_containers: HashMap<String, ContainerDefinition>,
+    /// Docker managed volumes that should be deleted once no container uses them
+    #[serde(default)]
+    pending_volume_removals: Vec<String>,
+    /// The Juju ports that have been opened for exposed container port bindings
+    #[serde(default)]
+    opened_ports: HashSet<String>,
+}
+
+// Registry credentials are masked so that they don't end up in the logs
+impl std::fmt::Debug for DaemonState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let masked = "********".to_string();
+        let kv: HashMap<&String, &String> = self
+            .kv
+            .iter()
+            .map(|(key, value)| {
+                if tools::is_credential_source(self, &CredentialSource::Kv(key.clone())) {
+                    (key, &masked)
+                } else {
+                    (key, &**value)
+                }
+            })
+            .collect();
+        let masked_config = JsonValue::String(masked.clone());
+        let charm_config: HashMap<&String, &JsonValue> = self
+            .charm_config
+            .iter()
+            .map(|(key, value)| {
+                if tools::is_credential_source(self, &CredentialSource::Config(key.clone())) {
+                    (key, &masked_config)
+                } else {
+                    (key, &**value)
+                }
+            })
+            .collect();
+
+        f.debug_struct("DaemonState")
+            .field("script_statuses", &self.script_statuses)
+            .field("kv", &kv)
+            .field("default_container", &self.default_container)
+            .field("named_containers", &self.named_containers)
+            .field("charm_config", &charm_config)
+            .field("declared_containers", &self.declared_containers)
+            .field("pending_volume_removals", &self.pending_volume_removals)
+            .field("opened_ports", &self.opened_ports)
+            .finish()
+    }
+}
+
+/// The name of the file in the Lucky data dir that the values of the key-value store entries and
+/// charm config options used as registry credentials are stored in
+const CREDENTIALS_FILE: &str = "credentials.yaml";
+
+/// The key-value store entries and charm config options used as registry credentials
+///
+/// These are written to their own file, that only root can read, instead of to the state file.
+#[derive(Default, Serialize, Deserialize)]
+struct StoredCredentials {
+    kv: HashMap<String, Cd<String>>,
+    charm_config: HashMap<String, Cd<JsonValue>>,
 }
 
 /// The Lucky Daemon RPC service
@@ -71,8 +144,14 @@ struct LuckyDaemon {
     state: Arc<RwLock<DaemonState>>,
     /// The last time that the cron tick was run
     last_cron_tick: Arc<Mutex<DateTime<Local>>>,
-    /// The docker daemon connection if it has been loaded
-    docker_conn: Arc<Mutex<Option<Arc<Mutex<Docker>>>>>,
+    /// The last time that the containers were reconciled with the Docker state
+    last_reconcile: Arc<Mutex<DateTime<Local>>>,
+    /// The last time that the container image registries were checked for image updates
+    last_image_check: Arc<Mutex<DateTime<Local>>>,
+    /// The container runtime selected in the lucky.yaml
+    runtime: Box<dyn ContainerRuntime>,
+    /// Whether or not the connection to the container runtime has been checked
+    runtime_connected: AtomicBool,
 }
 
 pub(crate) struct LuckyDaemonOptions {
@@ -83,6 +162,11 @@ pub(crate) struct LuckyDaemonOptions {
     pub stop_listening: Arc<AtomicBool>,
 }
 
+/// The number of minutes between periodic reconciliations of the containers with the Docker state
+const RECONCILE_INTERVAL_MINS: i64 = 5;
+/// The number of minutes between checks for newer versions of container images
+const IMAGE_CHECK_INTERVAL_MINS: i64 = 60;
+
 // TODO: set juju status upon errors
 // Utility macro for handling anyhow results with `handle_err!(function_that_returns_anyhow_err())`
 macro_rules! handle_err {
@@ -105,6 +189,8 @@ impl LuckyDaemon {
     /// actual stopping of the server itself is not handled by the daemon.
     fn new(options: LuckyDaemonOptions) -> Self {
         let daemon = LuckyDaemon {
+            runtime: new_runtime(&options.lucky_metadata),
+            runtime_connected: AtomicBool::new(false),
             lucky_metadata: options.lucky_metadata,
             charm_dir: options.charm_dir,
             lucky_data_dir: options.data_dir,
@@ -112,7 +198,8 @@ impl LuckyDaemon {
             stop_listening: options.stop_listening,
             state: Default::default(),
             last_cron_tick: Arc::new(Mutex::new(Local::now())),
-            docker_conn: Arc::new(Mutex::new(None)),
+            last_reconcile: Arc::new(Mutex::new(Local::now())),
+            last_image_check: Arc::new(Mutex::new(Local::now())),
         };
 
         // Load daemon state
@@ -128,33 +215,66 @@ impl LuckyDaemon {
 
         log::trace!("Loaded daemon state: {:#?}", daemon.state.read().unwrap());
 
+        // Re-create any containers that went missing while the daemon wasn't running. This is
+        // skipped if the container runtime can't be reached, which is the case on a fresh unit
+        // before the runtime is installed.
+        if daemon.lucky_metadata.use_docker {
+            if let Err(e) = daemon.get_runtime() {
+                log::debug!("Skipping container reconciliation: {:?}", e);
+            } else {
+                tools::reconcile_containers(&daemon)
+                    .context("Could not reconcile containers")
+                    .unwrap_or_else(|e| log::error!("{:?}", e));
+            }
+        }
+
         daemon
     }
 
-    /// Gets a handle to the daemon's Docker connection, creating a new one if one doesn't already
-    /// exist.
-    fn get_docker_conn(&self) -> anyhow::Result<Arc<Mutex<Docker>>> {
-        let mut docker_conn = self.docker_conn.lock().unwrap();
+    /// Gets the daemon's container runtime, making sure that it can be connected to the first time
+    /// that it is used
+    fn get_runtime(&self) -> anyhow::Result<&dyn ContainerRuntime> {
+        if !self.runtime_connected.load(Ordering::SeqCst) {
+            log::debug!("Connecting to {}", self.runtime.name());
+            self.runtime
+                .check_connection()
+                .context(format!("Could not connect to {}", self.runtime.name()))?;
+            self.runtime_connected.store(true, Ordering::SeqCst);
+        }
 
-        // If we have a connection already, return it
-        if let Some(docker_conn) = &*docker_conn {
-            Ok(docker_conn.clone())
-        // If there is no connection
-        } else {
-            // Connect to docker
-            log::debug!("Connecting to Docker");
-            let conn = Docker::new();
+        Ok(&*self.runtime)
+    }
 
-            // Test getting Docker info
-            log::trace!("Docker info: {:?}", crate::rt::block_on(conn.info())?);
+    /// Get the id of a container, returning `None` if the container has not been created yet and
+    /// an error if the container does not exist
+    fn find_container_id(&self, container_name: &Option<String>) -> anyhow::Result<Option<String>> {
+        let state = self.state.read().unwrap();
+
+        let container = match container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
 
-            // Return connection
-            let conn = Arc::new(Mutex::new(conn));
-            *docker_conn = Some(conn.clone());
-            Ok(conn)
+        match container {
+            Some(container) => Ok(container.id.clone()),
+            None => Err(anyhow::format_err!(
+                r#"Container "{}" does not exist"#,
+                container_name.as_deref().unwrap_or("default")
+            )),
         }
     }
 
+    /// Get the id of a running container, returning an error if the container does not exist or
+    /// has not been created yet
+    fn get_container_id(&self, container_name: &Option<String>) -> anyhow::Result<String> {
+        self.find_container_id(container_name)?.ok_or_else(|| {
+            anyhow::format_err!(
+                r#"Container "{}" has not been started"#,
+                container_name.as_deref().unwrap_or("default")
+            )
+        })
+    }
+
     #[allow(clippy::needless_pass_by_value)]
     fn _trigger_hook(
         &self,
@@ -383,6 +503,25 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         // Update the last cron tick
         *last_cron_tick = Local::now();
 
+        // Periodically re-create any containers that are missing or have drifted
+        if self.lucky_metadata.use_docker {
+            let mut last_reconcile = self.last_reconcile.lock().unwrap();
+            if Local::now() - *last_reconcile >= chrono::Duration::minutes(RECONCILE_INTERVAL_MINS)
+            {
+                handle_err!(tools::reconcile_containers(self), call);
+                *last_reconcile = Local::now();
+            }
+
+            // Periodically re-create containers that have newer images in their registries
+            let mut last_image_check = self.last_image_check.lock().unwrap();
+            if Local::now() - *last_image_check
+                >= chrono::Duration::minutes(IMAGE_CHECK_INTERVAL_MINS)
+            {
+                handle_err!(tools::check_image_updates(self), call);
+                *last_image_check = Local::now();
+            }
+        }
+
         // Unset the Juju context as it will be invalid when the cron tick command exits
         std::env::remove_var("JUJU_CONTEXT_ID");
 
@@ -457,7 +596,12 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         for (key, value) in data {
             // If a value has been provided
             if let Some(value) = value {
-                log::debug!("Key-Value set: {} = {}", key, value);
+                // Don't log values that are used as registry credentials
+                if tools::is_credential_source(&state, &CredentialSource::Kv(key.clone())) {
+                    log::debug!("Key-Value set: {} = ********", key);
+                } else {
+                    log::debug!("Key-Value set: {} = {}", key, value);
+                }
                 // Set key to value
                 state.kv.insert(key, value.into());
             } else {
@@ -581,6 +725,26 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         call.reply(handle_err!(juju::resource_get(&resource_name), call))
     }
 
+    fn template_render(
+        &self,
+        call: &mut dyn rpc::Call_TemplateRender,
+        template: String,
+        output: String,
+    ) -> varlink::Result<()> {
+        let output = PathBuf::from(output);
+        if !output.is_absolute() {
+            return call.reply_error(format!(
+                "Output path must be absolute: {}",
+                output.to_string_lossy()
+            ));
+        }
+
+        // Render the template
+        let changed = handle_err!(template::render_template(self, &template, &output), call);
+
+        call.reply(changed)
+    }
+
     fn port_open(&self, call: &mut dyn rpc::Call_PortOpen, port: String) -> varlink::Result<()> {
         log::debug!("Opening port: {}", port);
 
@@ -656,6 +820,9 @@ impl rpc::VarlinkInterface for LuckyDaemon {
             container.update(|c| c.pending_removal = true);
         }
 
+        // Close any ports that were opened for the container
+        handle_err!(tools::sync_exposed_ports(&mut state), call);
+
         // Reply empty
         call.reply()
     }
@@ -735,196 +902,251 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         call.reply()
     }
 
-    // The uncollapsed if is easier to understand in this case
-    #[allow(clippy::collapsible_if)]
-    fn container_image_set(
+    fn container_logs(
         &self,
-        call: &mut dyn rpc::Call_ContainerImageSet,
-        image: String,
+        call: &mut dyn rpc::Call_ContainerLogs,
+        tail: Option<i64>,
+        since: Option<i64>,
+        follow: bool,
         container_name: Option<String>,
-        no_pull: bool,
     ) -> varlink::Result<()> {
-        let mut state = self.state.write().unwrap();
+        // This call must be called with more
+        if !call.wants_more() {
+            call.reply_requires_more()?;
+            return Ok(());
+        }
 
-        // If this is for a named container
-        if let Some(name) = container_name {
-            if let Some(container) = state.named_containers.get_mut(&name) {
-                log::debug!("Set Docker image [{}]: {}", name, image);
-                // Set the image on existing container
-                container.update(|c| {
-                    c.config.image = image;
-                    c.pull_image = !no_pull;
-                });
-            } else {
-                log::debug!("Adding new docker container: {}", name);
-                log::debug!("Set Docker image [{}]: {}", name, image);
-                // Create a new container with the given image
-                let mut new_container = ContainerInfo::new(&image);
-                new_container.pull_image = !no_pull;
-                state.named_containers.insert(name, new_container.into());
+        // Following the logs holds onto the daemon connection until the client goes away, which
+        // would keep any hook that is running from being able to use the daemon
+        if follow && std::env::var("JUJU_CONTEXT_ID").is_ok() {
+            return call.reply_error(
+                "Container logs can't be followed from inside of a hook or while a hook is running"
+                    .into(),
+            );
+        }
+
+        // Get the container ID. This is scoped so that we don't keep the state locked while
+        // streaming the logs.
+        let container_id = {
+            let state = self.state.read().unwrap();
+
+            let container = match &container_name {
+                Some(container_name) => state.named_containers.get(container_name),
+                None => state.default_container.as_ref(),
+            };
+
+            match container.and_then(|c| c.id.clone()) {
+                Some(id) => id,
+                None => {
+                    return call.reply_error(format!(
+                        r#"Container "{}" does not exist or has not been started"#,
+                        container_name.as_ref().unwrap_or(&"default".into())
+                    ))
+                }
             }
-        // If this is for the default container
-        } else {
-            if let Some(container) = &mut state.default_container {
-                log::debug!("Set container image: {}", image);
-                // Set the image on existing container
-                container.update(|c| {
-                    c.config.image = image;
-                    c.pull_image = !no_pull;
-                });
-            } else {
-                log::debug!("Adding container");
-                log::debug!("Set container image: {}", image);
-                // Create a new container with the given image
-                let mut new_container = ContainerInfo::new(&image);
-                new_container.pull_image = !no_pull;
-                state.default_container = Some(new_container.into());
+        };
+
+        // Start streaming the logs
+        let logs = handle_err!(
+            tools::stream_container_logs(
+                self,
+                &container_id,
+                tail.and_then(|x| x.try_into().ok()),
+                since,
+                follow
+            ),
+            call
+        );
+
+        // Reply with each chunk of output
+        call.set_continues(true);
+        loop {
+            match logs.chunks.recv_timeout(std::time::Duration::from_secs(1)) {
+                Ok(Ok(chunk)) => call.reply(chunk.output, chunk.stderr)?,
+                Ok(Err(e)) => {
+                    call.set_continues(false);
+                    return call.reply_error(format!("Error reading container logs: {}", e));
+                }
+                // Send empty output while waiting for logs so that we find out if the client has
+                // gone away. Returning the error drops the log stream, which stops it.
+                Err(RecvTimeoutError::Timeout) => call.reply("".into(), false)?,
+                // The logs have ended
+                Err(RecvTimeoutError::Disconnected) => break,
             }
         }
 
-        // Reply empty
-        call.reply()
+        // Send the final reply
+        call.set_continues(false);
+        call.reply("".into(), false)
     }
 
-    // The uncollapsed if is easier to understand in this case
-    #[allow(clippy::collapsible_if)]
-    fn container_image_get(
+    fn container_exec(
         &self,
-        call: &mut dyn rpc::Call_ContainerImageGet,
+        call: &mut dyn rpc::Call_ContainerExec,
+        command: Vec<String>,
+        stdin: Option<String>,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
-        let state = self.state.read().unwrap();
+        // This call must be called with more
+        if !call.wants_more() {
+            call.reply_requires_more()?;
+            return Ok(());
+        }
 
-        // If this is for a named container
-        if let Some(name) = container_name {
-            if let Some(container) = state.named_containers.get(&name) {
-                call.reply(Some(container.config.image.clone()))
-            } else {
-                call.reply(None)
-            }
-        // If this is for the default container
-        } else {
-            if let Some(container) = &state.default_container {
-                call.reply(Some(container.config.image.clone()))
-            } else {
-                call.reply(None)
+        // Get the container ID
+        let container_id = handle_err!(self.get_container_id(&container_name), call);
+
+        log::info!(
+            "Executing command in container \"{}\": {:?}",
+            container_name.as_ref().unwrap_or(&"default".into()),
+            command
+        );
+
+        // Run the command
+        let output = handle_err!(
+            tools::exec_in_container(self, &container_id, &command, stdin),
+            call
+        );
+
+        // Reply with each chunk of output and then the exit code
+        call.set_continues(true);
+        for output in output {
+            match handle_err!(output, call) {
+                tools::ExecOutput::Stdout(output) => call.reply(output, false, None)?,
+                tools::ExecOutput::Stderr(output) => call.reply(output, true, None)?,
+                tools::ExecOutput::Exit(code) => {
+                    log::debug!("Container command exited: {}", code);
+                    call.set_continues(false);
+                    return call.reply("".into(), false, Some(code.into()));
+                }
             }
         }
+
+        // The output ended without an exit code
+        call.set_continues(false);
+        call.reply_error("Container command output ended without an exit code".into())
     }
 
-    fn container_env_get(
+    // The daemon runs as root and can't tell whether a caller is on the host or in one of the
+    // containers that the daemon socket is mounted into, so it trusts every caller with the host
+    // paths that they give. Refusing to copy when the client runs in a container is left to the
+    // client, which is the only one that knows where it is running.
+    fn container_copy_to(
         &self,
-        call: &mut dyn rpc::Call_ContainerEnvGet,
-        key: String,
+        call: &mut dyn rpc::Call_ContainerCopyTo,
+        host_path: String,
+        container_path: String,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
-        let state = self.state.read().unwrap();
+        let host_path = PathBuf::from(host_path);
+        if !host_path.is_absolute() {
+            return call.reply_error(format!(
+                "Host path must be absolute: {}",
+                host_path.to_string_lossy()
+            ));
+        }
 
-        // Get the config for the requested container
-        let container = match &container_name {
-            Some(container_name) => state.named_containers.get(container_name),
-            None => state.default_container.as_ref(),
-        };
+        // Get the container ID
+        let container_id = handle_err!(self.get_container_id(&container_name), call);
 
-        // If the specified container exists
-        if let Some(container) = container {
-            // Reply with the environment variable's value
-            call.reply(container.config.env_vars.get(&key).map(ToOwned::to_owned))
-        } else {
-            // Reply with None
-            call.reply(None)
-        }
+        log::debug!(
+            "Copying {} to container \"{}\": {}",
+            host_path.to_string_lossy(),
+            container_name.as_deref().unwrap_or("default"),
+            container_path
+        );
+
+        // Copy the file into the container
+        let runtime = handle_err!(self.get_runtime(), call);
+        handle_err!(
+            crate::docker::copy_to_container(runtime, &container_id, &host_path, &container_path),
+            call
+        );
+
+        // Reply empty
+        call.reply()
     }
 
-    fn container_env_get_all(
+    // See `container_copy_to` for how host paths are trusted
+    fn container_copy_from(
         &self,
-        call: &mut dyn rpc::Call_ContainerEnvGetAll,
+        call: &mut dyn rpc::Call_ContainerCopyFrom,
+        container_path: String,
+        host_path: String,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
-        let state = self.state.read().unwrap();
-
-        // This call must be called with more
-        if !call.wants_more() {
-            call.reply_requires_more()?;
-            return Ok(());
+        let host_path = PathBuf::from(host_path);
+        if !host_path.is_absolute() {
+            return call.reply_error(format!(
+                "Host path must be absolute: {}",
+                host_path.to_string_lossy()
+            ));
         }
 
-        // Get the config for the requested container
-        let container = match &container_name {
-            Some(container_name) => state.named_containers.get(container_name),
-            None => state.default_container.as_ref(),
-        };
+        // Get the container ID
+        let container_id = handle_err!(self.get_container_id(&container_name), call);
 
-        // If the container exists
-        if let Some(container) = container {
-            // Loop through key-value pairs and return result to client
-            let pairs: Vec<rpc::ContainerEnvGetAll_Reply_pairs> = container
-                .config
-                .env_vars
-                .iter()
-                .map(|(k, v)| rpc::ContainerEnvGetAll_Reply_pairs {
-                    key: k.clone(),
-                    value: v.clone(),
-                })
-                .collect();
+        log::debug!(
+            "Copying {} from container \"{}\": {}",
+            container_path,
+            container_name.as_deref().unwrap_or("default"),
+            host_path.to_string_lossy()
+        );
 
-            // Reply with pairs
-            call.reply(pairs)
+        // Copy the file out of the container
+        let runtime = handle_err!(self.get_runtime(), call);
+        handle_err!(
+            crate::docker::copy_from_container(runtime, &container_id, &container_path, &host_path),
+            call
+        );
 
-        // If the container doesn't exist
-        } else {
-            // Reply with empty array
-            call.reply(vec![])
-        }
+        // Reply empty
+        call.reply()
     }
 
-    /// Set a value in the unit local key-value store
-    fn container_env_set(
+    fn container_status(
         &self,
-        call: &mut dyn rpc::Call_ContainerEnvSet,
-        vars: HashMap<String, Option<String>>,
+        call: &mut dyn rpc::Call_ContainerStatus,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
-        let mut state = self.state.write().unwrap();
-
-        // Get the config for the requested container
-        let mut container = match &container_name {
-            Some(container_name) => state.named_containers.get_mut(container_name),
-            None => state.default_container.as_mut(),
-        };
-
-        if let Some(container) = &mut container {
-            for (key, value) in vars {
-                // If a value has been provided
-                if let Some(value) = value {
-                    log::debug!("Container env set: {} = {}", key, value);
-                    // Set key to value
-                    container.update(|c| {
-                        c.config.env_vars.insert(key, value);
-                    });
-                } else {
-                    log::debug!("Container env deleted: {}", key);
-                    // Erase key
-                    container.update(|c| {
-                        c.config.env_vars.remove(&key);
-                    });
-                }
+        let container_id = handle_err!(self.find_container_id(&container_name), call);
+
+        // Reply `missing` if the container hasn't been created or has been removed from the
+        // container runtime
+        let status = match container_id {
+            Some(id) => {
+                let runtime = handle_err!(self.get_runtime(), call);
+                handle_err!(crate::docker::get_container_status(runtime, &id), call)
             }
-        }
+            None => None,
+        };
+        let status = match status {
+            Some(status) => status,
+            None => return call.reply("missing".into(), None, None, None, None, None, None),
+        };
 
-        // Reply empty
-        call.reply()
+        call.reply(
+            status.state,
+            Some(status.exit_code),
+            Some(status.restart_count),
+            status.started_at,
+            status.health.map(|x| x.as_ref().to_string()),
+            Some(status.image_id),
+            status.image_digest,
+        )
     }
 
-    fn container_volume_add(
+    fn container_set_restart_policy(
         &self,
-        call: &mut dyn rpc::Call_ContainerVolumeAdd,
-        source: String,
-        target: String,
+        call: &mut dyn rpc::Call_ContainerSetRestartPolicy,
+        policy: String,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
+        // Parse the restart policy
+        let policy: RestartPolicy = handle_err!(policy.parse(), call);
+
         // Get the config for the requested container
         let mut container_log_name = None;
         let mut container = match &container_name {
@@ -937,34 +1159,1338 @@ impl rpc::VarlinkInterface for LuckyDaemon {
 
         if let Some(container) = &mut container {
             log::debug!(
-                "Creating container volume{}: {}:{}",
+                "Setting container restart policy{}: {}",
                 container_log_name.map_or("".into(), |x| format!("[{}]", x)),
-                source,
-                target
+                policy
             );
-            // Add volume to container config
-            container.update(|c| {
-                c.config
-                    .volumes
-                    .insert(VolumeTarget(target), VolumeSource(source));
-            });
+
+            container.update(|c| c.config.restart_policy = policy);
         }
 
         // Reply empty
         call.reply()
     }
 
-    fn container_volume_remove(
+    fn container_set_update_strategy(
         &self,
-        call: &mut dyn rpc::Call_ContainerVolumeRemove,
-        target: String,
-        delete_data: bool,
+        call: &mut dyn rpc::Call_ContainerSetUpdateStrategy,
+        strategy: String,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
-        // Get the config for the requested container
-        let mut container_log_name = None;
+        // Parse the update strategy
+        let strategy: UpdateStrategy = handle_err!(
+            strategy
+                .parse()
+                .map_err(|_| anyhow::format_err!("Invalid update strategy: {}", strategy)),
+            call
+        );
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container update strategy{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                strategy.as_ref()
+            );
+
+            container.update(|c| c.config.update_strategy = strategy);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_set_stop_signal(
+        &self,
+        call: &mut dyn rpc::Call_ContainerSetStopSignal,
+        signal: Option<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Make sure the signal is a signal name or number
+        let signal = signal.map(|x| x.to_uppercase());
+        if let Some(signal) = &signal {
+            let re = regex::Regex::new(r"^((SIG)?[A-Z][A-Z0-9+-]*|[0-9]+)$")
+                .expect("Could not compile regex");
+            if !re.is_match(signal) {
+                call.reply_error(format!("Invalid stop signal: {}", signal))?;
+                return Ok(());
+            }
+        }
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container stop signal{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                signal.as_ref().unwrap_or(&"unset".to_string()),
+            );
+
+            container.update(|c| c.config.stop_signal = signal);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_set_stop_timeout(
+        &self,
+        call: &mut dyn rpc::Call_ContainerSetStopTimeout,
+        timeout: Option<i64>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        let timeout: Option<u64> = match timeout {
+            Some(timeout) => Some(handle_err!(
+                timeout.try_into().context("Invalid stop timeout"),
+                call
+            )),
+            None => None,
+        };
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container stop timeout{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                timeout.map_or("unset".into(), |x| format!("{}s", x)),
+            );
+
+            container.update(|c| c.config.stop_timeout = timeout);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_depends_on_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerDependsOnSet,
+        dependencies: Vec<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Parse the dependencies
+        let dependencies: Vec<ContainerDependency> = handle_err!(
+            dependencies
+                .iter()
+                .map(|x| x.parse())
+                .collect::<anyhow::Result<_>>(),
+            call
+        );
+
+        // A container can't depend on itself
+        if let Some(name) = &container_name {
+            if dependencies.iter().any(|x| &x.container == name) {
+                return call.reply_error(format!(r#"Container "{}" can't depend on itself"#, name));
+            }
+        }
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container dependencies{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                dependencies
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            container.update(|c| c.config.depends_on = dependencies);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_depends_on_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerDependsOnGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // Reply with the dependencies
+        call.reply(container.map_or(vec![], |container| {
+            container
+                .config
+                .depends_on
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        }))
+    }
+
+    // The uncollapsed if is easier to understand in this case
+    #[allow(clippy::collapsible_if)]
+    fn container_image_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerImageSet,
+        image: String,
+        container_name: Option<String>,
+        no_pull: bool,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // If this is for a named container
+        if let Some(name) = container_name {
+            if let Some(container) = state.named_containers.get_mut(&name) {
+                log::debug!("Set Docker image [{}]: {}", name, image);
+                // Set the image on existing container
+                container.update(|c| {
+                    c.config.image = image;
+                    if no_pull {
+                        c.pull_policy = PullPolicy::Never;
+                    }
+                    c.image_resource = None;
+                });
+            } else {
+                log::debug!("Adding new docker container: {}", name);
+                log::debug!("Set Docker image [{}]: {}", name, image);
+                // Create a new container with the given image
+                let mut new_container = ContainerInfo::new(&image);
+                if no_pull {
+                    new_container.pull_policy = PullPolicy::Never;
+                }
+                state.named_containers.insert(name, new_container.into());
+            }
+        // If this is for the default container
+        } else {
+            if let Some(container) = &mut state.default_container {
+                log::debug!("Set container image: {}", image);
+                // Set the image on existing container
+                container.update(|c| {
+                    c.config.image = image;
+                    if no_pull {
+                        c.pull_policy = PullPolicy::Never;
+                    }
+                    c.image_resource = None;
+                });
+            } else {
+                log::debug!("Adding container");
+                log::debug!("Set container image: {}", image);
+                // Create a new container with the given image
+                let mut new_container = ContainerInfo::new(&image);
+                if no_pull {
+                    new_container.pull_policy = PullPolicy::Never;
+                }
+                state.default_container = Some(new_container.into());
+            }
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_image_set_from_resource(
+        &self,
+        call: &mut dyn rpc::Call_ContainerImageSetFromResource,
+        resource_name: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        // Load the image from the resource. This is done before locking the state because loading
+        // a large image can take a long time.
+        let (image, resource) = handle_err!(tools::load_image_resource(self, &resource_name), call);
+
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(name) => state.named_containers.get_mut(name),
+            None => state.default_container.as_mut(),
+        };
+        let container_log_name = container_name
+            .as_ref()
+            .map_or("".into(), |x| format!("[{}]", x));
+
+        if let Some(container) = container {
+            log::debug!("Set container image{}: {}", container_log_name, image);
+            // Set the image on existing container
+            container.update(|c| {
+                c.config.image = image.clone();
+                c.pull_policy = PullPolicy::Never;
+                c.image_resource = Some(resource);
+            });
+        } else {
+            log::debug!("Adding container{}", container_log_name);
+            log::debug!("Set container image{}: {}", container_log_name, image);
+            // Create a new container with the loaded image
+            let mut new_container = ContainerInfo::new(&image);
+            new_container.pull_policy = PullPolicy::Never;
+            new_container.image_resource = Some(resource);
+
+            if let Some(name) = container_name {
+                state.named_containers.insert(name, new_container.into());
+            } else {
+                state.default_container = Some(new_container.into());
+            }
+        }
+
+        // Reply with the loaded image
+        call.reply(image)
+    }
+
+    // The uncollapsed if is easier to understand in this case
+    #[allow(clippy::collapsible_if)]
+    fn container_image_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerImageGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // If this is for a named container
+        if let Some(name) = container_name {
+            if let Some(container) = state.named_containers.get(&name) {
+                call.reply(Some(container.config.image.clone()))
+            } else {
+                call.reply(None)
+            }
+        // If this is for the default container
+        } else {
+            if let Some(container) = &state.default_container {
+                call.reply(Some(container.config.image.clone()))
+            } else {
+                call.reply(None)
+            }
+        }
+    }
+
+    fn container_image_set_pull_policy(
+        &self,
+        call: &mut dyn rpc::Call_ContainerImageSetPullPolicy,
+        policy: String,
+        check_updates: bool,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Parse the pull policy
+        let policy: PullPolicy = handle_err!(
+            policy
+                .parse()
+                .map_err(|_| anyhow::format_err!("Invalid pull policy: {}", policy)),
+            call
+        );
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container pull policy{}: {}{}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                policy.as_ref(),
+                if check_updates {
+                    " ( checking for updates )"
+                } else {
+                    ""
+                }
+            );
+
+            container.update(|c| {
+                c.pull_policy = policy;
+                c.check_image_updates = check_updates;
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_image_upgrade(
+        &self,
+        call: &mut dyn rpc::Call_ContainerImageUpgrade,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        if !self.lucky_metadata.use_docker {
+            return call.reply(false);
+        }
+
+        // Pull the latest image and re-create the container if it is outdated
+        let upgraded = handle_err!(
+            tools::upgrade_container_image(self, container_name.as_deref()),
+            call
+        );
+
+        call.reply(upgraded)
+    }
+
+    fn container_env_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerEnvGet,
+        key: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // If the specified container exists
+        if let Some(container) = container {
+            // Reply with the environment variable's value
+            call.reply(container.config.env_vars.get(&key).map(ToOwned::to_owned))
+        } else {
+            // Reply with None
+            call.reply(None)
+        }
+    }
+
+    fn container_env_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerEnvGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // This call must be called with more
+        if !call.wants_more() {
+            call.reply_requires_more()?;
+            return Ok(());
+        }
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // If the container exists
+        if let Some(container) = container {
+            // Loop through key-value pairs and return result to client
+            let pairs: Vec<rpc::ContainerEnvGetAll_Reply_pairs> = container
+                .config
+                .env_vars
+                .iter()
+                .map(|(k, v)| rpc::ContainerEnvGetAll_Reply_pairs {
+                    key: k.clone(),
+                    value: v.clone(),
+                })
+                .collect();
+
+            // Reply with pairs
+            call.reply(pairs)
+
+        // If the container doesn't exist
+        } else {
+            // Reply with empty array
+            call.reply(vec![])
+        }
+    }
+
+    /// Set a value in the unit local key-value store
+    fn container_env_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerEnvSet,
+        vars: HashMap<String, Option<String>>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container = match &container_name {
+            Some(container_name) => state.named_containers.get_mut(container_name),
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            for (key, value) in vars {
+                // If a value has been provided
+                if let Some(value) = value {
+                    log::debug!("Container env set: {} = {}", key, value);
+                    // Set key to value
+                    container.update(|c| {
+                        c.config.env_vars.insert(key, value);
+                    });
+                } else {
+                    log::debug!("Container env deleted: {}", key);
+                    // Erase key
+                    container.update(|c| {
+                        c.config.env_vars.remove(&key);
+                    });
+                }
+            }
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_volume_add(
+        &self,
+        call: &mut dyn rpc::Call_ContainerVolumeAdd,
+        source: String,
+        target: String,
+        mount_type: Option<String>,
+        read_only: bool,
+        propagation: Option<String>,
+        tmpfs_size: Option<i64>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Parse the mount options
+        let mount_type = match mount_type {
+            Some(mount_type) => handle_err!(
+                mount_type
+                    .parse()
+                    .map_err(|_| anyhow::format_err!("Invalid mount type: {}", mount_type)),
+                call
+            ),
+            None => Default::default(),
+        };
+        let propagation = match propagation {
+            Some(propagation) => Some(handle_err!(
+                propagation
+                    .parse()
+                    .map_err(|_| anyhow::format_err!("Invalid mount propagation: {}", propagation)),
+                call
+            )),
+            None => None,
+        };
+        let mount = VolumeMount {
+            read_only,
+            propagation,
+            tmpfs_size,
+            ..VolumeMount::new(mount_type, &source)
+        };
+        handle_err!(mount.validate(), call);
+        if !target.starts_with('/') {
+            call.reply_error(format!(
+                "Volume target must be an absolute path: {}",
+                target
+            ))?;
+            return Ok(());
+        }
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Creating container volume{}: {}:{}:{}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                source,
+                target,
+                mount.options().join(",")
+            );
+            // Add volume to container config
+            container.update(|c| {
+                c.config.volumes.insert(VolumeTarget(target), mount);
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_volume_remove(
+        &self,
+        call: &mut dyn rpc::Call_ContainerVolumeRemove,
+        target: String,
+        delete_data: bool,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+        let state = &mut *state;
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        // If the specified container exists
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Deleting container volume{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                target
+            );
+
+            // Remove the container volume
+            let pending_volume_removals = &mut state.pending_volume_removals;
+            container.update(|container| {
+                let volumes = &mut container.config.volumes;
+
+                // Get source and remove from volume list
+                let mount = volumes.remove(&VolumeTarget(target));
+
+                // If there is a volume for the given target path
+                if let Some(mount) = mount {
+                    // If we should delete the source data
+                    if delete_data {
+                        // If there are no other volumes with the same source
+                        if volumes
+                            .values()
+                            .find(|&x| x.mount_type == mount.mount_type && x.source == mount.source)
+                            .is_none()
+                        {
+                            let source = &mount.source;
+                            match mount.mount_type {
+                                MountType::Bind => {
+                                    log::debug!("Deleting volume data source: {}", &**source);
+
+                                    // Delete data
+                                    if source.starts_with('/') {
+                                        handle_err!(std::fs::remove_dir_all(&**source), call);
+                                    } else {
+                                        handle_err!(
+                                            std::fs::remove_dir_all(
+                                                self.lucky_data_dir
+                                                    .join(VOLUME_DIR)
+                                                    .join(&**source)
+                                            ),
+                                            call
+                                        );
+                                    }
+                                }
+                                MountType::Volume => {
+                                    // The volume can't be removed while the container still uses
+                                    // it so it is removed after the container has been updated
+                                    log::debug!("Scheduling volume for removal: {}", &**source);
+                                    pending_volume_removals.push((**source).clone());
+                                }
+                                // Tmpfs mounts don't have any data to delete
+                                MountType::Tmpfs => return call.reply(false),
+                            }
+
+                            call.reply(true /* data deleted */)?;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                call.reply(false /* no data deleted */)
+            })
+
+        // If the specified container didn't exist
+        } else {
+            call.reply(false /* no data deleted */)
+        }
+    }
+
+    fn container_volume_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerVolumeGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container = match &container_name {
+            Some(container_name) => state.named_containers.get_mut(container_name),
+            None => state.default_container.as_mut(),
+        };
+
+        // If the container exists
+        if let Some(container) = &mut container {
+            // Reply wth volumes
+            call.reply(
+                container
+                    .config
+                    .volumes
+                    .iter()
+                    .map(|(target, mount)| rpc::ContainerVolumeGetAll_Reply_volumes {
+                        source: (*mount.source).clone(),
+                        target: (**target).clone(),
+                        options: mount.options(),
+                    })
+                    .collect(),
+            )
+        } else {
+            // Reply empty
+            call.reply(vec![])
+        }
+    }
+
+    fn container_port_add(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPortAdd,
+        port_binding: String,
+        expose: bool,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+        let port_binding: PortBinding = handle_err!(port_binding.parse(), call);
+        if expose && port_binding.protocol == "sctp" {
+            call.reply_error(format!(
+                "Juju can't open SCTP ports, not exposing port binding: {}",
+                port_binding
+            ))?;
+            return Ok(());
+        }
+
+        // If another container of the unit binds any of the same host ports
+        if let Some((other_name, offending_binding)) = state
+            .named_containers
+            .iter()
+            .map(|(name, container)| (Some(name), container))
+            .chain(state.default_container.iter().map(|c| (None, c)))
+            .filter(|(name, container)| {
+                name.map(String::as_str) != container_name.as_deref() && !container.pending_removal
+            })
+            .flat_map(|(name, container)| container.config.ports.iter().map(move |b| (name, b)))
+            .find(|(_, b)| b.host_ports_overlap(&port_binding))
+        {
+            // Throw an error because both containers can't bind the same host port
+            call.reply_error(format!(
+                concat!(
+                    "Not adding port binding `{}` because it conflicts with the port binding ",
+                    "`{}` of the {} container"
+                ),
+                port_binding,
+                offending_binding,
+                other_name.map_or("default".into(), |x| format!("`{}`", x))
+            ))?;
+            return Ok(());
+        }
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Adding port to container{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                port_binding
+            );
+
+            // If there are other port bindings with any of the same host or container ports but
+            // isn't the exact same port binding
+            if let Some(offending_binding) = container.config.ports.iter().find(|&b| {
+                (b.host_ports_overlap(&port_binding) || b.container_ports_overlap(&port_binding))
+                    && b != &port_binding
+            }) {
+                // Throw an error because we can't add port binding that has the same port as
+                // another.
+                call.reply_error(format!(
+                    concat!(
+                        "Not adding port binding `{}` because it conflicts with a port binding ",
+                        "already added to the container: {}"
+                    ),
+                    port_binding, offending_binding
+                ))?;
+                return Ok(());
+            }
+
+            container.update(|c| {
+                if expose {
+                    c.exposed_ports.insert(port_binding.clone());
+                } else {
+                    c.exposed_ports.remove(&port_binding);
+                }
+                c.config.ports.insert(port_binding);
+            });
+        }
+
+        // Open or close the port in Juju
+        handle_err!(tools::sync_exposed_ports(&mut state), call);
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_port_remove(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPortRemove,
+        port_binding: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+        let port_binding: PortBinding = handle_err!(port_binding.parse(), call);
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Removing port from container{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                port_binding
+            );
+
+            container.update(|c| {
+                c.exposed_ports.remove(&port_binding);
+                c.config.ports.remove(&port_binding);
+            });
+        }
+
+        // Close the port in Juju if it was exposed
+        handle_err!(tools::sync_exposed_ports(&mut state), call);
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_port_remove_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPortRemoveAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = container {
+            // For each port
+            for port_binding in &container.config.ports.clone() {
+                log::debug!(
+                    "Removing port from container{}: {}",
+                    container_log_name
+                        .as_ref()
+                        .map_or("".into(), |x| format!("[{}]", x)),
+                    port_binding
+                );
+
+                // Remove the port
+                container.update(|c| {
+                    c.exposed_ports.remove(&port_binding);
+                    c.config.ports.remove(&port_binding);
+                });
+            }
+        }
+
+        // Close any ports that were exposed
+        handle_err!(tools::sync_exposed_ports(&mut state), call);
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_port_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPortGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let mut container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        if let Some(container) = &mut container {
+            call.reply(
+                container
+                    .config
+                    .ports
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            )
+        } else {
+            // Reply empty
+            call.reply(vec![])
+        }
+    }
+
+    fn container_network_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerNetworkSet,
+        network_name: Option<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container network{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                network_name.as_ref().unwrap_or(&"unset".to_string()),
+            );
+
+            container.update(|c| c.config.network = network_name);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_network_join(
+        &self,
+        call: &mut dyn rpc::Call_ContainerNetworkJoin,
+        network_name: String,
+        scope: String,
+        aliases: Vec<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Parse the network scope
+        let scope: NetworkScope = handle_err!(
+            scope
+                .parse()
+                .map_err(|_| anyhow::format_err!("Invalid network scope: {}", scope)),
+            call
+        );
+
+        // The container can always be reached by its Lucky name, or the app name for the default
+        // container
+        let mut aliases = aliases;
+        let implicit_alias = match &container_name {
+            Some(name) => name.clone(),
+            None => crate::docker::app_name(&std::env::var("JUJU_UNIT_NAME").unwrap_or_default())
+                .to_string(),
+        };
+        if !implicit_alias.is_empty() && !aliases.contains(&implicit_alias) {
+            aliases.insert(0, implicit_alias);
+        }
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Joining container to network{}: {} ( {} ) with aliases: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                network_name,
+                scope.as_ref(),
+                aliases.join(", ")
+            );
+
+            container.update(|c| {
+                c.config
+                    .networks
+                    .insert(network_name, NetworkAttachment { scope, aliases })
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_network_leave(
+        &self,
+        call: &mut dyn rpc::Call_ContainerNetworkLeave,
+        network_name: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Removing container from network{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                network_name
+            );
+
+            container.update(|c| c.config.networks.remove(&network_name));
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_network_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerNetworkGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // Reply with the container's networks
+        call.reply(container.map_or(vec![], |container| {
+            container
+                .config
+                .networks
+                .iter()
+                .map(
+                    |(name, attachment)| rpc::ContainerNetworkGetAll_Reply_networks {
+                        network_name: name.clone(),
+                        scope: attachment.scope.as_ref().into(),
+                        aliases: attachment.aliases.clone(),
+                    },
+                )
+                .collect()
+        }))
+    }
+
+    fn container_hostname_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerHostnameSet,
+        hostname: Option<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container hostname{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                hostname.as_ref().unwrap_or(&"unset".to_string()),
+            );
+
+            container.update(|c| c.config.hostname = hostname);
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_label_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerLabelSet,
+        labels: HashMap<String, Option<String>>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container = match &container_name {
+            Some(container_name) => state.named_containers.get_mut(container_name),
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            for (key, value) in labels {
+                // If a value has been provided
+                if let Some(value) = value {
+                    log::debug!("Container label set: {} = {}", key, value);
+                    // Set label to value
+                    container.update(|c| {
+                        c.config.labels.insert(key, value);
+                    });
+                } else {
+                    log::debug!("Container label deleted: {}", key);
+                    // Erase label
+                    container.update(|c| {
+                        c.config.labels.remove(&key);
+                    });
+                }
+            }
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_label_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerLabelGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // Reply with the labels
+        call.reply(container.map_or_else(HashMap::new, |c| c.config.labels.clone()))
+    }
+
+    fn container_extra_host_add(
+        &self,
+        call: &mut dyn rpc::Call_ContainerExtraHostAdd,
+        hostname: String,
+        ip: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Adding container extra host{}: {} {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                ip,
+                hostname
+            );
+
+            container.update(|c| {
+                c.config.extra_hosts.insert(hostname, ip);
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_extra_host_remove(
+        &self,
+        call: &mut dyn rpc::Call_ContainerExtraHostRemove,
+        hostname: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Removing container extra host{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                hostname
+            );
+
+            container.update(|c| {
+                c.config.extra_hosts.remove(&hostname);
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_extra_host_get_all(
+        &self,
+        call: &mut dyn rpc::Call_ContainerExtraHostGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        if let Some(container) = container {
+            call.reply(
+                container
+                    .config
+                    .extra_hosts
+                    .iter()
+                    .map(|(hostname, ip)| rpc::ContainerExtraHostGetAll_Reply_hosts {
+                        hostname: hostname.clone(),
+                        ip: ip.clone(),
+                    })
+                    .collect(),
+            )
+        } else {
+            // Reply empty
+            call.reply(vec![])
+        }
+    }
+
+    fn container_dns_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerDnsSet,
+        servers: Vec<String>,
+        search_domains: Vec<String>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Setting container DNS{}: servers: [{}], search domains: [{}]",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                servers.join(", "),
+                search_domains.join(", ")
+            );
+
+            container.update(|c| {
+                c.config.dns_servers = servers;
+                c.config.dns_search_domains = search_domains;
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_dns_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerDnsGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        if let Some(container) = container {
+            call.reply(
+                container.config.dns_servers.clone(),
+                container.config.dns_search_domains.clone(),
+            )
+        } else {
+            // Reply empty
+            call.reply(vec![], vec![])
+        }
+    }
+
+    fn container_privileged_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPrivilegedSet,
+        privileged: bool,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
         let mut container = match &container_name {
             Some(container_name) => {
                 container_log_name = Some(container_name.clone());
@@ -973,101 +2499,111 @@ impl rpc::VarlinkInterface for LuckyDaemon {
             None => state.default_container.as_mut(),
         };
 
-        // If the specified container exists
         if let Some(container) = &mut container {
+            let container_log_name = container_log_name.map_or("".into(), |x| format!("[{}]", x));
+
+            // A warning is logged when the container is created in privileged mode
             log::debug!(
-                "Deleting container volume{}: {}",
-                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
-                target
+                "{} privileged mode for container{}",
+                if privileged { "Enabling" } else { "Disabling" },
+                container_log_name
             );
 
-            // Remove the container volume
-            container.update(|container| {
-                let volumes = &mut container.config.volumes;
-
-                // Get source and remove from volume list
-                let source = volumes.remove(&VolumeTarget(target));
+            container.update(|c| c.config.privileged = privileged);
+        }
 
-                // If there is a volume for the given target path
-                if let Some(source) = source {
-                    // If we should delete the source data
-                    if delete_data {
-                        // If there are no other volumes with the same source
-                        if volumes.values().find(|&x| *x == source).is_none() {
-                            log::debug!("Deleting volume data source: {}", &*source);
-
-                            // Delete data
-                            if source.starts_with('/') {
-                                handle_err!(std::fs::remove_dir_all(&*source), call);
-                            } else {
-                                handle_err!(
-                                    std::fs::remove_dir_all(
-                                        self.lucky_data_dir.join(VOLUME_DIR).join(&*source)
-                                    ),
-                                    call
-                                );
-                            }
+        // Reply empty
+        call.reply()
+    }
 
-                            call.reply(true /* data deleted */)?;
-                            return Ok(());
-                        }
-                    }
-                }
+    fn container_privileged_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerPrivilegedGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
 
-                call.reply(false /* no data deleted */)
-            })
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
 
-        // If the specified container didn't exist
-        } else {
-            call.reply(false /* no data deleted */)
-        }
+        call.reply(container.map_or(false, |c| c.config.privileged))
     }
 
-    fn container_volume_get_all(
+    fn container_capabilities_set(
         &self,
-        call: &mut dyn rpc::Call_ContainerVolumeGetAll,
+        call: &mut dyn rpc::Call_ContainerCapabilitiesSet,
+        add: Vec<String>,
+        drop: Vec<String>,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
         // Get the config for the requested container
+        let mut container_log_name = None;
         let mut container = match &container_name {
-            Some(container_name) => state.named_containers.get_mut(container_name),
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
             None => state.default_container.as_mut(),
         };
 
-        // If the container exists
         if let Some(container) = &mut container {
-            // Reply wth volumes
+            log::debug!(
+                "Setting container capabilities{}: add: [{}], drop: [{}]",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                add.join(", "),
+                drop.join(", ")
+            );
+
+            container.update(|c| {
+                c.config.cap_add = add;
+                c.config.cap_drop = drop;
+            });
+        }
+
+        // Reply empty
+        call.reply()
+    }
+
+    fn container_capabilities_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerCapabilitiesGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        if let Some(container) = container {
             call.reply(
-                container
-                    .config
-                    .volumes
-                    .iter()
-                    .map(
-                        |(target, source)| rpc::ContainerVolumeGetAll_Reply_volumes {
-                            source: (**source).clone(),
-                            target: (**target).clone(),
-                        },
-                    )
-                    .collect(),
+                container.config.cap_add.clone(),
+                container.config.cap_drop.clone(),
             )
         } else {
             // Reply empty
-            call.reply(vec![])
+            call.reply(vec![], vec![])
         }
     }
 
-    fn container_port_add(
+    fn container_device_add(
         &self,
-        call: &mut dyn rpc::Call_ContainerPortAdd,
-        host_port: i64,
-        container_port: i64,
-        protocol: String,
+        call: &mut dyn rpc::Call_ContainerDeviceAdd,
+        device: String,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
+        // Parse the device mapping
+        let device: DeviceMapping = handle_err!(device.parse(), call);
+
         // Get the config for the requested container
         let mut container_log_name = None;
         let mut container = match &container_name {
@@ -1080,51 +2616,53 @@ impl rpc::VarlinkInterface for LuckyDaemon {
 
         if let Some(container) = &mut container {
             log::debug!(
-                "Adding port to container{}: {}:{}/{}",
+                "Adding container device{}: {}",
                 container_log_name.map_or("".into(), |x| format!("[{}]", x)),
-                host_port,
-                container_port,
-                protocol
+                device
             );
 
-            let host_port = handle_err!(host_port.try_into().context("Invalid port number"), call);
-            let container_port = handle_err!(
-                container_port.try_into().context("Invalid port number"),
-                call
-            );
+            container.update(|c| {
+                // Replace any device mapped to the same container path
+                c.config
+                    .devices
+                    .retain(|d| d.container_path != device.container_path);
+                c.config.devices.push(device);
+            });
+        }
 
-            let port_binding = PortBinding {
-                host_port,
-                container_port,
-                protocol,
-            };
+        // Reply empty
+        call.reply()
+    }
 
-            // If there are other port bindings with the same protocol and host or container port
-            // but isn't the exact same port binding
-            if let Some(offending_binding) = container.config.ports.iter().find(|&b| {
-                // With the same host port
-                (b.host_port == port_binding.host_port
-                        // or with the same container port
-                        || b.container_port == port_binding.container_port)
-                        // and with the same protocol
-                        && b.protocol == port_binding.protocol
-                        // and not the same exact port binding
-                        && b != &port_binding
-            }) {
-                // Throw an error because we can't add port binding that has the same port as
-                // another.
-                call.reply_error(format!(
-                    concat!(
-                        "Not adding port binding `{}` because it conflicts with a port binding ",
-                        "already added to the container: {}"
-                    ),
-                    port_binding, offending_binding
-                ))?;
-                return Ok(());
+    fn container_device_remove(
+        &self,
+        call: &mut dyn rpc::Call_ContainerDeviceRemove,
+        container_path: String,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
             }
+            None => state.default_container.as_mut(),
+        };
+
+        if let Some(container) = &mut container {
+            log::debug!(
+                "Removing container device{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                container_path
+            );
 
             container.update(|c| {
-                c.config.ports.insert(port_binding);
+                c.config
+                    .devices
+                    .retain(|d| d.container_path != container_path);
             });
         }
 
@@ -1132,12 +2670,28 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         call.reply()
     }
 
-    fn container_port_remove(
+    fn container_device_get_all(
         &self,
-        call: &mut dyn rpc::Call_ContainerPortRemove,
-        host_port: i64,
-        container_port: i64,
-        protocol: String,
+        call: &mut dyn rpc::Call_ContainerDeviceGetAll,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        call.reply(container.map_or_else(Vec::new, |c| {
+            c.config.devices.iter().map(ToString::to_string).collect()
+        }))
+    }
+
+    fn container_security_opt_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerSecurityOptSet,
+        options: Vec<String>,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
@@ -1154,44 +2708,45 @@ impl rpc::VarlinkInterface for LuckyDaemon {
 
         if let Some(container) = &mut container {
             log::debug!(
-                "Removing port from container{}: {}:{}/{}",
+                "Setting container security options{}: [{}]",
                 container_log_name.map_or("".into(), |x| format!("[{}]", x)),
-                host_port,
-                container_port,
-                protocol
+                options.join(", ")
             );
 
-            container.update(|c| {
-                c.config.ports.remove(&PortBinding {
-                    host_port: handle_err!(
-                        host_port.try_into().context("Invalid port number"),
-                        call
-                    ),
-                    container_port: handle_err!(
-                        container_port.try_into().context("Invalid port number"),
-                        call
-                    ),
-                    protocol,
-                });
-
-                Ok(())
-            })?;
+            container.update(|c| c.config.security_opts = options);
         }
 
         // Reply empty
         call.reply()
     }
 
-    fn container_port_remove_all(
+    fn container_security_opt_get(
         &self,
-        call: &mut dyn rpc::Call_ContainerPortRemoveAll,
+        call: &mut dyn rpc::Call_ContainerSecurityOptGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        call.reply(container.map_or_else(Vec::new, |c| c.config.security_opts.clone()))
+    }
+
+    fn container_resources_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerResourcesSet,
+        limits: HashMap<String, Option<i64>>,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
         // Get the config for the requested container
         let mut container_log_name = None;
-        let container = match &container_name {
+        let mut container = match &container_name {
             Some(container_name) => {
                 container_log_name = Some(container_name.clone());
                 state.named_containers.get_mut(container_name)
@@ -1199,70 +2754,129 @@ impl rpc::VarlinkInterface for LuckyDaemon {
             None => state.default_container.as_mut(),
         };
 
-        if let Some(container) = container {
-            // For each port
-            for port_binding in &container.config.ports.clone() {
+        if let Some(container) = &mut container {
+            // Validate the limits before modifying the container
+            let mut resources = container.config.resources.clone();
+            for (name, value) in limits {
                 log::debug!(
-                    "Removing port from container{}: {}:{}/{}",
+                    "Setting container resource limit{}: {} = {}",
                     container_log_name
                         .as_ref()
                         .map_or("".into(), |x| format!("[{}]", x)),
-                    port_binding.host_port,
-                    port_binding.container_port,
-                    port_binding.protocol
+                    name,
+                    value.map_or("unset".into(), |x| x.to_string())
                 );
 
-                // Remove the port
-                container.update(|c| {
-                    c.config.ports.remove(&port_binding);
-                });
+                *handle_err!(resources.get_mut(&name), call) = value;
             }
+
+            container.update(|c| c.config.resources = resources);
         }
 
         // Reply empty
         call.reply()
     }
 
-    fn container_port_get_all(
+    fn container_resources_get(
         &self,
-        call: &mut dyn rpc::Call_ContainerPortGetAll,
+        call: &mut dyn rpc::Call_ContainerResourcesGet,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let state = self.state.read().unwrap();
 
         // Get the config for the requested container
-        let mut container = match &container_name {
+        let container = match &container_name {
             Some(container_name) => state.named_containers.get(container_name),
             None => state.default_container.as_ref(),
         };
 
+        // Reply with the resource limits that have been set
+        call.reply(container.map_or_else(HashMap::new, |c| {
+            c.config
+                .resources
+                .to_vec()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect()
+        }))
+    }
+
+    fn container_healthcheck_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerHealthcheckSet,
+        healthcheck: Option<rpc::ContainerHealthcheck>,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let mut state = self.state.write().unwrap();
+
+        // Get the config for the requested container
+        let mut container_log_name = None;
+        let mut container = match &container_name {
+            Some(container_name) => {
+                container_log_name = Some(container_name.clone());
+                state.named_containers.get_mut(container_name)
+            }
+            None => state.default_container.as_mut(),
+        };
+
         if let Some(container) = &mut container {
-            call.reply(
-                container
-                    .config
-                    .ports
-                    .iter()
-                    .map(|port| rpc::ContainerPortGetAll_Reply_ports {
-                        container_port: port.container_port.into(),
-                        host_port: port.host_port.into(),
-                        protocol: port.protocol.clone(),
-                    })
-                    .collect(),
-            )
-        } else {
-            // Reply empty
-            call.reply(vec![])
+            let healthcheck: Option<HealthCheck> = healthcheck.map(Into::into);
+
+            log::debug!(
+                "Setting container health check{}: {}",
+                container_log_name.map_or("".into(), |x| format!("[{}]", x)),
+                healthcheck
+                    .as_ref()
+                    .map_or("unset".into(), |x| x.cmd.clone()),
+            );
+
+            container.update(|c| c.config.healthcheck = healthcheck);
         }
+
+        // Reply empty
+        call.reply()
     }
 
-    fn container_network_set(
+    fn container_healthcheck_get(
         &self,
-        call: &mut dyn rpc::Call_ContainerNetworkSet,
-        network_name: Option<String>,
+        call: &mut dyn rpc::Call_ContainerHealthcheckGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // Reply with the health check if the container exists and has one
+        call.reply(
+            container
+                .and_then(|c| c.config.healthcheck.clone())
+                .map(Into::into),
+        )
+    }
+
+    fn container_registry_auth_set(
+        &self,
+        call: &mut dyn rpc::Call_ContainerRegistryAuthSet,
+        auth: Option<rpc::ContainerRegistryAuth>,
         container_name: Option<String>,
     ) -> varlink::Result<()> {
         let mut state = self.state.write().unwrap();
 
+        // Parse the credential sources
+        let credentials = if let Some(auth) = auth {
+            Some(RegistryCredentials {
+                server: auth.server,
+                username: handle_err!(auth.username.parse::<CredentialSource>(), call),
+                password: handle_err!(auth.password.parse::<CredentialSource>(), call),
+            })
+        } else {
+            None
+        };
+
         // Get the config for the requested container
         let mut container_log_name = None;
         let mut container = match &container_name {
@@ -1274,18 +2888,47 @@ impl rpc::VarlinkInterface for LuckyDaemon {
         };
 
         if let Some(container) = &mut container {
+            // Only the credential sources are logged, never the credentials
             log::debug!(
-                "Setting container network{}: {}",
+                "Setting container registry credentials{}: {}",
                 container_log_name.map_or("".into(), |x| format!("[{}]", x)),
-                network_name.as_ref().unwrap_or(&"unset".to_string()),
+                credentials.as_ref().map_or("unset".into(), |x| format!(
+                    "username from {}, password from {}",
+                    x.username, x.password
+                )),
             );
 
-            container.update(|c| c.config.network = network_name);
+            container.update(|c| c.registry_credentials = credentials);
         }
 
         // Reply empty
         call.reply()
     }
+
+    fn container_registry_auth_get(
+        &self,
+        call: &mut dyn rpc::Call_ContainerRegistryAuthGet,
+        container_name: Option<String>,
+    ) -> varlink::Result<()> {
+        let state = self.state.read().unwrap();
+
+        // Get the config for the requested container
+        let container = match &container_name {
+            Some(container_name) => state.named_containers.get(container_name),
+            None => state.default_container.as_ref(),
+        };
+
+        // Reply with the credential sources if the container exists and has them
+        call.reply(
+            container
+                .and_then(|c| c.registry_credentials.as_ref())
+                .map(|credentials| rpc::ContainerRegistryAuth {
+                    server: credentials.server.clone(),
+                    username: credentials.username.to_string(),
+                    password: credentials.password.to_string(),
+                }),
+        )
+    }
 }
 
 impl Drop for LuckyDaemon {
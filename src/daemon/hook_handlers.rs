@@ -1,10 +1,7 @@
 //! Built-in handlers for Juju hooks that are executed by the daemon
 
-use std::time::Duration;
-
 use super::*;
 use crate::docker::ContainerInfo;
-use crate::rt::block_on;
 use crate::types::{ScriptState, ScriptStatus};
 
 pub(super) fn handle_pre_hook(daemon: &LuckyDaemon, hook_name: &str) -> anyhow::Result<()> {
@@ -12,12 +9,14 @@ pub(super) fn handle_pre_hook(daemon: &LuckyDaemon, hook_name: &str) -> anyhow::
         "install" => handle_pre_install(daemon),
         "config-changed" => handle_pre_config_changed(daemon),
         "upgrade-charm" => handle_pre_upgrade_charm(daemon),
+        "update-status" => handle_pre_update_status(daemon),
         _ => Ok(()),
     }
 }
 
 pub(super) fn handle_post_hook(daemon: &LuckyDaemon, hook_name: &str) -> anyhow::Result<()> {
     match hook_name {
+        "install" | "config-changed" => handle_post_declared_containers(daemon),
         "stop" => handle_post_stop(daemon),
         _ => Ok(()),
     }
@@ -32,10 +31,18 @@ fn handle_pre_install(daemon: &LuckyDaemon) -> anyhow::Result<()> {
 
     // If Docker support is enabled
     if daemon.lucky_metadata.use_docker {
-        daemon_set_status!(&mut state, ScriptState::Maintenance, "Installing docker");
+        daemon_set_status!(
+            &mut state,
+            ScriptState::Maintenance,
+            format!("Installing {}", daemon.runtime.name())
+        );
+
+        // Make sure the container runtime is installed
+        daemon.runtime.ensure_installed()?;
 
-        // Make sure Docker is installed
-        crate::docker::ensure_docker()?;
+        // Create the containers declared in the lucky.yaml
+        tools::apply_container_declarations(daemon, &mut state)
+            .context("Could not apply containers declared in lucky.yaml")?;
 
         daemon_set_status!(&mut state, ScriptState::Active);
     }
@@ -49,31 +56,53 @@ fn handle_pre_config_changed(daemon: &LuckyDaemon) -> anyhow::Result<()> {
     // Update the configuration cache
     update_config_cache(&mut state)?;
 
+    // Update the containers declared in the lucky.yaml with the new config
+    if daemon.lucky_metadata.use_docker {
+        tools::apply_container_declarations(daemon, &mut state)
+            .context("Could not apply containers declared in lucky.yaml")?;
+    }
+
+    Ok(())
+}
+
+/// Apply updates to the containers declared in the lucky.yaml after the charm scripts have run in
+/// case there were no scripts for the hook to apply them
+fn handle_post_declared_containers(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    if daemon.lucky_metadata.use_docker && !daemon.lucky_metadata.containers.is_empty() {
+        tools::apply_container_updates(&daemon)
+            .context("Could not apply updates to containers declared in lucky.yaml")?;
+    }
+
     Ok(())
 }
 
 #[function_name::named]
 fn handle_post_stop(daemon: &LuckyDaemon) -> anyhow::Result<()> {
     let mut state = daemon.state.write().unwrap();
-    let docker_conn = daemon.get_docker_conn()?;
-    let docker_conn = docker_conn.lock().unwrap();
+    let runtime = daemon.get_runtime()?;
 
     daemon_set_status!(&mut state, ScriptState::Maintenance, "Removing containers");
 
     for mut container_info in state.named_containers.values_mut() {
-        remove_container(&docker_conn, &mut container_info)?;
+        remove_container(runtime, &mut container_info)?;
     }
 
     // Erase container config
     state.named_containers.clear();
 
     if let Some(container_info) = &mut state.default_container {
-        remove_container(&docker_conn, container_info)?;
+        remove_container(runtime, container_info)?;
     }
 
     // Erase container config
     state.default_container = None;
 
+    // Remove the unit's networks
+    tools::remove_unused_networks(runtime, &state, true)?;
+
+    // Close any ports that were exposed for the containers
+    tools::sync_exposed_ports(&mut state)?;
+
     daemon_set_status!(&mut state, ScriptState::Active);
     Ok(())
 }
@@ -81,7 +110,32 @@ fn handle_post_stop(daemon: &LuckyDaemon) -> anyhow::Result<()> {
 #[function_name::named]
 fn handle_pre_upgrade_charm(daemon: &LuckyDaemon) -> anyhow::Result<()> {
     let mut state = daemon.state.write().unwrap();
-    daemon_set_status!(&mut state, ScriptState::Maintenance, "Updating containers after charm upgrade");
+    daemon_set_status!(
+        &mut state,
+        ScriptState::Maintenance,
+        "Updating containers after charm upgrade"
+    );
+    drop(state);
+
+    // Apply any changes to the container runtime settings in the lucky.yaml
+    if daemon.lucky_metadata.use_docker {
+        daemon
+            .runtime
+            .ensure_installed()
+            .context(format!("Could not configure {}", daemon.runtime.name()))?;
+    }
+
+    // Reload any images that come from Juju resources that have been updated
+    tools::refresh_resource_images(daemon)
+        .context("Could not refresh container images from resources")?;
+
+    let mut state = daemon.state.write().unwrap();
+
+    // Update the containers declared in the lucky.yaml
+    if daemon.lucky_metadata.use_docker {
+        tools::apply_container_declarations(daemon, &mut state)
+            .context("Could not apply containers declared in lucky.yaml")?;
+    }
 
     // Mark any containers as dirty because they need to be restarted
     if let Some(container) = &mut state.default_container {
@@ -90,7 +144,7 @@ fn handle_pre_upgrade_charm(daemon: &LuckyDaemon) -> anyhow::Result<()> {
     for container in &mut state.named_containers.values_mut() {
         container.mark_dirty();
     }
-    
+
     // Drop state while we apply container updates
     drop(state);
 
@@ -103,6 +157,20 @@ fn handle_pre_upgrade_charm(daemon: &LuckyDaemon) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn handle_pre_update_status(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    // If Docker support is enabled
+    if daemon.lucky_metadata.use_docker {
+        // Re-create any containers that are missing or have drifted from their configuration
+        tools::reconcile_containers(&daemon).context("Could not reconcile containers")?;
+
+        // Reflect container health in the unit status
+        tools::update_container_health_status(&daemon)
+            .context("Could not check container health")?;
+    }
+
+    Ok(())
+}
+
 //
 // Helpers
 //
@@ -133,20 +201,18 @@ fn update_config_cache(state: &mut DaemonState) -> anyhow::Result<()> {
 
 /// Helper to remove a given container
 fn remove_container(
-    docker_conn: &shiplift::Docker,
+    runtime: &dyn ContainerRuntime,
     container_info: &mut Cd<ContainerInfo>,
 ) -> anyhow::Result<()> {
     // If container has an ID
     if let Some(id) = &container_info.id {
-        let container = docker_conn.containers().get(id);
-
-        // Stop the container
+        // Stop the container. The running container was created from the original config.
         log::debug!("Stopping container: {}", id);
-        block_on(container.stop(Some(Duration::from_secs(10))))?;
+        runtime.stop_container(id, container_info.original().config.stop_timeout())?;
 
         // Remove the container
         log::debug!("Removing container: {}", id);
-        block_on(container.delete())?;
+        runtime.remove_container(id)?;
 
         // Unset the container id
         container_info.update(|info| info.id = None);
@@ -0,0 +1,253 @@
+//! Config file templating for charm scripts
+use handlebars::Handlebars;
+use serde_json::{json, Map as JsonMap};
+
+use std::fs;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Component, Path, PathBuf};
+
+use super::*;
+use crate::config::load_yaml;
+use crate::types::juju::CharmMetadata;
+
+/// Build the template context from the charm config, unit key-value store, leader data, relation
+/// data, and unit addresses
+fn get_template_context(daemon: &LuckyDaemon) -> anyhow::Result<JsonValue> {
+    let (config, kv) = {
+        let state = daemon.state.read().unwrap();
+        (
+            state
+                .charm_config
+                .iter()
+                .map(|(k, v)| (k.clone(), (**v).clone()))
+                .collect::<JsonMap<String, JsonValue>>(),
+            state
+                .kv
+                .iter()
+                .map(|(k, v)| (k.clone(), JsonValue::String((**v).clone())))
+                .collect::<JsonMap<String, JsonValue>>(),
+        )
+    };
+
+    // Get the data for every relation in the charm metadata
+    let charm_metadata: CharmMetadata = load_yaml(&daemon.charm_dir, "metadata")?;
+    let mut relations = JsonMap::new();
+    for relation_name in charm_metadata
+        .provides
+        .iter()
+        .chain(charm_metadata.requires.iter())
+        .chain(charm_metadata.peers.iter())
+        .flat_map(|relations| relations.keys())
+    {
+        let mut relation_instances = vec![];
+        for relation_id in juju::relation_ids(relation_name)? {
+            let mut units = vec![];
+            for unit in juju::relation_list(Some(relation_id.clone()))? {
+                let data = juju::relation_get(
+                    Some(juju::SpecificRelation {
+                        relation_id: relation_id.clone(),
+                        remote_unit: unit.clone(),
+                    }),
+                    false,
+                )?;
+                units.push(json!({ "name": unit, "data": data }));
+            }
+            relation_instances.push(json!({ "id": relation_id, "units": units }));
+        }
+        relations.insert(relation_name.clone(), relation_instances.into());
+    }
+
+    Ok(json!({
+        "unit_name": std::env::var("JUJU_UNIT_NAME").unwrap_or_default(),
+        "config": config,
+        "kv": kv,
+        "is_leader": juju::is_leader()?,
+        "leader": juju::leader_get()?,
+        "relations": relations,
+        "private_address": juju::unit_get_private_address()?,
+        "public_address": juju::unit_get_public_address()?,
+    }))
+}
+
+/// Get a copy of the template context with the registry credentials masked so that it can be
+/// logged
+fn mask_credentials(daemon: &LuckyDaemon, context: &JsonValue) -> JsonValue {
+    let state = daemon.state.read().unwrap();
+    let mut context = context.clone();
+    let sections: [(&str, fn(String) -> CredentialSource); 2] = [
+        ("kv", CredentialSource::Kv),
+        ("config", CredentialSource::Config),
+    ];
+    for (section, source) in sections.iter() {
+        if let Some(values) = context.get_mut(*section).and_then(JsonValue::as_object_mut) {
+            for (key, value) in values.iter_mut() {
+                if tools::is_credential_source(&state, &source(key.clone())) {
+                    *value = JsonValue::String("********".into());
+                }
+            }
+        }
+    }
+
+    context
+}
+
+/// Get the path to a template in the charm directory
+///
+/// The template path must be relative and stay inside of the charm directory, even after
+/// following symlinks, so that charm scripts can't use the daemon to read other files on the host.
+fn get_template_path(charm_dir: &Path, template: &str) -> anyhow::Result<PathBuf> {
+    let template = Path::new(template);
+    if !template.components().all(|component| match component {
+        Component::Normal(_) | Component::CurDir => true,
+        _ => false,
+    }) {
+        anyhow::bail!(
+            "Template path must be relative to the charm directory: {}",
+            template.to_string_lossy()
+        );
+    }
+
+    let template_path = charm_dir.join(template);
+    let canonical_path = template_path.canonicalize().context(format!(
+        "Could not read template: {}",
+        template_path.to_string_lossy()
+    ))?;
+    if !canonical_path.starts_with(charm_dir.canonicalize()?) {
+        anyhow::bail!(
+            "Template is not inside of the charm directory: {}",
+            template_path.to_string_lossy()
+        );
+    }
+
+    Ok(canonical_path)
+}
+
+/// Render a template from the charm directory to the output file, returning whether or not the
+/// file was changed
+///
+/// The output is written to a temporary file next to the output file and then moved into place
+/// so that nothing ever sees a partially written file. The output file isn't touched if the
+/// rendered content is the same as what is already there.
+pub(super) fn render_template(
+    daemon: &LuckyDaemon,
+    template: &str,
+    output: &Path,
+) -> anyhow::Result<bool> {
+    let template_path = get_template_path(&daemon.charm_dir, template)?;
+    let template_source = fs::read_to_string(&template_path).context(format!(
+        "Could not read template: {}",
+        template_path.to_string_lossy()
+    ))?;
+
+    // Render the template. The output is a config file, not HTML, so it is not escaped.
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    let context = get_template_context(daemon)?;
+    log::trace!(
+        "Template context: {:#?}",
+        mask_credentials(daemon, &context)
+    );
+    let rendered = handlebars
+        .render_template(&template_source, &context)
+        .context(format!(
+            "Could not render template: {}",
+            template_path.to_string_lossy()
+        ))?;
+
+    // Skip writing the file if it is unchanged
+    if let Ok(existing) = fs::read(output) {
+        if existing == rendered.as_bytes() {
+            log::debug!(
+                "Rendered template is unchanged: {}",
+                output.to_string_lossy()
+            );
+            return Ok(false);
+        }
+    }
+
+    // Write the rendered template to a temporary file in the same directory as the output
+    let parent = output
+        .parent()
+        .ok_or_else(|| anyhow::format_err!("Invalid output path: {}", output.to_string_lossy()))?;
+    let file_name = output
+        .file_name()
+        .ok_or_else(|| anyhow::format_err!("Invalid output path: {}", output.to_string_lossy()))?;
+    fs::create_dir_all(parent).context(format!(
+        "Could not create directory: {}",
+        parent.to_string_lossy()
+    ))?;
+    // The temporary file has a unique name so that renders of the same file at the same time don't
+    // write to the same temporary file. It is only readable by root until it has the permissions
+    // of the existing file because the template may contain secrets.
+    let temp_path = parent.join(format!(
+        ".{}.{:016x}.lucky-tmp",
+        file_name.to_string_lossy(),
+        rand::random::<u64>()
+    ));
+    let mut temp_file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .context(format!(
+            "Could not create file: {}",
+            temp_path.to_string_lossy()
+        ))?;
+    let result = (|| {
+        temp_file.write_all(rendered.as_bytes())?;
+        temp_file.sync_all()?;
+
+        // Keep the permissions of the existing file
+        if let Ok(metadata) = fs::metadata(output) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+
+        // Move the new file into place
+        log::debug!("Writing rendered template: {}", output.to_string_lossy());
+        fs::rename(&temp_path, output).context(format!(
+            "Could not write file: {}",
+            output.to_string_lossy()
+        ))
+    })();
+
+    // Don't leave the temporary file behind if the file couldn't be written
+    if result.is_err() {
+        fs::remove_file(&temp_path).ok();
+    }
+    result?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_path_must_be_in_charm_dir() {
+        let charm_dir =
+            std::env::temp_dir().join(format!("lucky-test-{}-template", std::process::id()));
+        fs::create_dir_all(charm_dir.join("templates")).unwrap();
+        fs::write(charm_dir.join("templates/app.conf.hbs"), "").unwrap();
+        std::os::unix::fs::symlink("/etc", charm_dir.join("templates/etc")).unwrap();
+
+        let result = std::panic::catch_unwind(|| {
+            assert_eq!(
+                get_template_path(&charm_dir, "templates/app.conf.hbs").unwrap(),
+                charm_dir
+                    .canonicalize()
+                    .unwrap()
+                    .join("templates/app.conf.hbs")
+            );
+            assert!(get_template_path(&charm_dir, "./templates/app.conf.hbs").is_ok());
+
+            // Absolute paths, `..`, and symlinks out of the charm directory are rejected
+            assert!(get_template_path(&charm_dir, "/etc/hostname").is_err());
+            assert!(get_template_path(&charm_dir, "templates/../../etc/hostname").is_err());
+            assert!(get_template_path(&charm_dir, "templates/etc/hostname").is_err());
+        });
+
+        fs::remove_dir_all(&charm_dir).unwrap();
+        result.unwrap();
+    }
+}
@@ -1,17 +1,29 @@
-use anyhow::format_err;
+use anyhow::{bail, format_err};
 use futures::prelude::*;
-use shiplift::{builder::ExecContainerOptions, PullOptions};
+use handlebars::Handlebars;
+use shiplift::RegistryAuth;
 use subprocess::{Exec, ExitStatus, Redirection};
 
+use std::collections::HashSet;
 use std::env;
+use std::fs::Permissions;
 use std::io::{BufRead, BufReader};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use crate::docker::ContainerInfo;
-use crate::rt::block_on;
+use crate::docker::runtime::{ContainerRuntime, LogOptions, OutputChunk};
+use crate::docker::{
+    connect_network, ensure_network, get_container_health, list_managed_networks,
+    other_units_have_containers, parse_volume, update_container_resources, ContainerInfo,
+    CredentialSource, DependencyCondition, HealthCheck, HealthStatus, ImageResource, MountType,
+    NetworkScope, PortBinding, PullPolicy, RegistryCredentials, RestartPolicy, UpdateStrategy,
+    CONFIG_HASH_LABEL,
+};
+use crate::rt::{block_on, spawn};
 use crate::types::{
-    CharmScript, CharmScriptType, ScriptState, ScriptStatus, LUCKY_EXIT_CODE_HELPER_PREFIX,
+    CharmScript, CharmScriptType, ContainerDefinition, ScriptState, ScriptStatus,
+    LUCKY_EXIT_CODE_HELPER_PREFIX,
 };
 
 use super::*;
@@ -29,11 +41,32 @@ pub(super) fn load_state(daemon: &LuckyDaemon) -> anyhow::Result<()> {
         .open(&state_file_path)
         .context(format!("Could not open state file: {:?}", state_file_path))?;
 
-    *daemon.state.write().unwrap() = serde_yaml::from_reader(state_file).context(format!(
+    let mut state: DaemonState = serde_yaml::from_reader(state_file).context(format!(
         "Could not parse state file as yaml: {:?}",
         state_file_path
     ))?;
 
+    // Restore the registry credentials, which are stored separately from the rest of the state
+    let credentials_file_path = daemon.lucky_data_dir.join(CREDENTIALS_FILE);
+    if credentials_file_path.exists() {
+        let credentials_file = OpenOptions::new()
+            .read(true)
+            .open(&credentials_file_path)
+            .context(format!(
+                "Could not open credentials file: {:?}",
+                credentials_file_path
+            ))?;
+        let credentials: StoredCredentials =
+            serde_yaml::from_reader(credentials_file).context(format!(
+                "Could not parse credentials file as yaml: {:?}",
+                credentials_file_path
+            ))?;
+        state.kv.extend(credentials.kv);
+        state.charm_config.extend(credentials.charm_config);
+    }
+
+    *daemon.state.write().unwrap() = state;
+
     Ok(())
 }
 
@@ -55,17 +88,60 @@ pub(super) fn flush_state(daemon: &LuckyDaemon) -> anyhow::Result<()> {
             state_file_path
         ))?;
 
-    // Serialize state to file
+    // Split the registry credentials out of the state so that they aren't written to the state
+    // file
     let state = &*daemon.state.read().unwrap();
     log::trace!("{:#?}", state);
-    serde_yaml::to_writer(state_file, state).context(format!(
+    let mut state_yaml = serde_yaml::to_value(state)?;
+    let mut credentials = StoredCredentials::default();
+    for (key, value) in &state.kv {
+        if is_credential_source(state, &CredentialSource::Kv(key.clone())) {
+            credentials.kv.insert(key.clone(), value.clone());
+            remove_yaml_key(&mut state_yaml, "kv", key);
+        }
+    }
+    for (key, value) in &state.charm_config {
+        if is_credential_source(state, &CredentialSource::Config(key.clone())) {
+            credentials.charm_config.insert(key.clone(), value.clone());
+            remove_yaml_key(&mut state_yaml, "charm_config", key);
+        }
+    }
+
+    // Serialize state to file
+    serde_yaml::to_writer(state_file, &state_yaml).context(format!(
         "Failed to serialize daemon state to file: {:?}",
         state_file_path
     ))?;
 
+    // Write the credentials to a file that only root can read
+    let credentials_file_path = daemon.lucky_data_dir.join(CREDENTIALS_FILE);
+    let credentials_file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .mode(0o600)
+        .open(&credentials_file_path)?;
+    // The mode is only used when the file is created so make sure an existing file has it too
+    credentials_file.set_permissions(Permissions::from_mode(0o600))?;
+    serde_yaml::to_writer(credentials_file, &credentials).context(format!(
+        "Failed to serialize credentials to file: {:?}",
+        credentials_file_path
+    ))?;
+
     Ok(())
 }
 
+/// Remove a key from one of the maps in the serialized daemon state
+fn remove_yaml_key(state_yaml: &mut serde_yaml::Value, map: &str, key: &str) {
+    if let Some(map) = state_yaml
+        .as_mapping_mut()
+        .and_then(|state| state.get_mut(&map.into()))
+        .and_then(serde_yaml::Value::as_mapping_mut)
+    {
+        map.remove(&key.into());
+    }
+}
+
 /// Set the status of a script
 pub(super) fn set_script_status(
     state: &mut DaemonState,
@@ -440,11 +516,8 @@ fn run_container_script(
         };
     }
 
-    // Get the docker connection
-    let docker_conn = daemon.get_docker_conn()?;
-    let docker_conn = docker_conn.lock().unwrap();
-    let containers = docker_conn.containers();
-    let container = containers.get(&container_id);
+    // Get the container runtime
+    let runtime = daemon.get_runtime()?;
 
     // The command environment
     let mut env: Vec<String> = environment
@@ -495,22 +568,15 @@ fn run_container_script(
         cmd
     );
 
-    // Build exec options
-    let exec_options = ExecContainerOptions::builder()
-        .attach_stderr(true)
-        .attach_stdout(true)
-        .env(env.iter().map(AsRef::as_ref).collect())
-        .cmd(cmd.iter().map(AsRef::as_ref).collect())
-        .build();
-
     // Instantiate exit code
     let exit_code: Arc<Mutex<Option<i32>>> = Arc::new(Mutex::new(None));
     let exit_code_ = exit_code.clone();
 
     // Exec script and log output
-    block_on(container.exec(&exec_options).for_each(move |chunk| {
+    let output = runtime.exec(&container_id, &cmd, &env)?;
+    block_on(output.for_each(move |chunk| {
         let exit_code = &exit_code_;
-        let chunk_str = chunk.as_string_lossy();
+        let chunk_str = &chunk.output;
 
         // TODO: https://github.com/softprops/shiplift/issues/219
         // This hack looks for a special prefix for a line of text that will tell us the exit
@@ -527,17 +593,14 @@ fn run_container_script(
                     .trim_start_matches(LUCKY_EXIT_CODE_HELPER_PREFIX)
                     .parse()
                     .map_err(|e| {
-                        shiplift::Error::InvalidResponse(format!(
-                            "Could not parse container script exit code: {}",
-                            e
-                        ))
+                        format_err!("Could not parse container script exit code: {}", e)
                     })?,
             );
 
         // If line doesn't start with exit-code prefix
         } else {
             // Log the output
-            log::debug!("output: {}", chunk.as_string_lossy());
+            log::debug!("output: {}", chunk.output);
         }
         Ok(())
     }))
@@ -562,6 +625,559 @@ fn run_container_script(
     }
 }
 
+/// Load a container image from a Juju resource, returning the image reference and the resource
+/// info to store with the container
+pub(super) fn load_image_resource(
+    daemon: &LuckyDaemon,
+    resource_name: &str,
+) -> anyhow::Result<(String, ImageResource)> {
+    // Get the path to the resource
+    let path = PathBuf::from(juju::resource_get(resource_name)?);
+    let fingerprint = ImageResource::fingerprint_file(&path)?;
+
+    // Load the image
+    log::debug!("Loading container image from resource: {}", resource_name);
+    let image = daemon.get_runtime()?.load_image(&path).context(format!(
+        "Could not load image from resource: {}",
+        resource_name
+    ))?;
+    log::debug!(
+        "Loaded container image from resource {}: {}",
+        resource_name,
+        image
+    );
+
+    Ok((
+        image,
+        ImageResource {
+            name: resource_name.into(),
+            fingerprint,
+        },
+    ))
+}
+
+/// Reload the images of any containers that use an image from a Juju resource if a new revision of
+/// the resource has been attached
+pub(super) fn refresh_resource_images(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    // Find the containers that use an image resource. This is scoped so that the state isn't
+    // locked while the images are loaded, which can take a long time.
+    let to_check = {
+        let state = daemon.state.read().unwrap();
+        state
+            .named_containers
+            .iter()
+            .map(|(name, container)| (Some(name.clone()), container))
+            .chain(
+                state
+                    .default_container
+                    .iter()
+                    .map(|container| (None, container)),
+            )
+            .filter_map(|(name, container)| Some((name, container.image_resource.clone()?)))
+            .collect::<Vec<_>>()
+    };
+
+    // Load the resources that have changed. Each resource is only loaded once even if more than
+    // one container uses it.
+    let mut loaded: HashMap<String, (String, ImageResource)> = HashMap::new();
+    for (name, resource) in to_check {
+        if !loaded.contains_key(&resource.name) {
+            // Skip resources that haven't changed
+            let path = PathBuf::from(juju::resource_get(&resource.name)?);
+            if ImageResource::fingerprint_file(&path)? == resource.fingerprint {
+                continue;
+            }
+
+            // Load the new image
+            log::info!("Image resource has changed, reloading: {}", resource.name);
+            loaded.insert(
+                resource.name.clone(),
+                load_image_resource(daemon, &resource.name)?,
+            );
+        }
+        let (image, new_resource) = loaded[&resource.name].clone();
+
+        // Update the container unless it was given a different image while the image was loading
+        let mut state = daemon.state.write().unwrap();
+        let container = match &name {
+            Some(name) => state.named_containers.get_mut(name),
+            None => state.default_container.as_mut(),
+        };
+        if let Some(container) = container {
+            if container.image_resource.as_ref() == Some(&resource) {
+                container.update(|c| {
+                    c.config.image = image;
+                    c.image_resource = Some(new_resource);
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Interpolate the charm config and key-value store into the environment variables of a container
+/// declared in the `lucky.yaml` file and check that its volumes and ports are valid
+fn interpolate_container_definition(
+    state: &DaemonState,
+    name: &str,
+    definition: &ContainerDefinition,
+) -> anyhow::Result<ContainerDefinition> {
+    let context = serde_json::json!({
+        "config": state
+            .charm_config
+            .iter()
+            .map(|(k, v)| (k.clone(), (**v).clone()))
+            .collect::<HashMap<String, JsonValue>>(),
+        "kv": state
+            .kv
+            .iter()
+            .map(|(k, v)| (k.clone(), (**v).clone()))
+            .collect::<HashMap<String, String>>(),
+    });
+
+    // Render the environment variables. Like `lucky template render`, config and keys that don't
+    // exist render as empty strings, because the declarations are first applied in the install hook
+    // before any keys could have been set.
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    let mut env = HashMap::new();
+    for (key, value) in &definition.env {
+        let value = handlebars
+            .render_template(value, &context)
+            .context(format!(
+                "Could not render environment variable `{}` for container `{}`",
+                key, name
+            ))?;
+        env.insert(key.clone(), value);
+    }
+
+    for volume in &definition.volumes {
+        parse_volume(volume).context(format!(
+            "Invalid volume `{}` for container `{}`",
+            volume, name
+        ))?;
+    }
+    for port in &definition.ports {
+        port.parse::<PortBinding>().context(format!(
+            "Invalid port binding `{}` for container `{}`",
+            port, name
+        ))?;
+    }
+
+    Ok(ContainerDefinition {
+        env,
+        ..definition.clone()
+    })
+}
+
+/// Update the container configuration from the containers declared in the `lucky.yaml` file
+///
+/// Only the settings that have changed in the declaration since it was last applied are updated
+/// so that changes made by charm scripts at runtime are kept until the declaration itself
+/// changes. Containers that are no longer declared are removed.
+pub(super) fn apply_container_declarations(
+    daemon: &LuckyDaemon,
+    state: &mut DaemonState,
+) -> anyhow::Result<()> {
+    let declarations = &daemon.lucky_metadata.containers;
+    if declarations.is_empty() && state.declared_containers.is_empty() {
+        return Ok(());
+    }
+    log::debug!("Applying container declarations from lucky.yaml");
+
+    // Interpolate all of the declarations before changing anything
+    let mut declared_containers = HashMap::new();
+    for (name, definition) in declarations {
+        declared_containers.insert(
+            name.clone(),
+            interpolate_container_definition(state, name, definition)?,
+        );
+    }
+
+    for (name, definition) in &declared_containers {
+        let previous = state.declared_containers.get(name);
+        if name == "default" {
+            apply_container_definition(name, &mut state.default_container, definition, previous);
+        } else {
+            let mut container = state.named_containers.remove(name);
+            apply_container_definition(name, &mut container, definition, previous);
+            if let Some(container) = container {
+                state.named_containers.insert(name.clone(), container);
+            }
+        }
+    }
+
+    // Remove containers that are no longer declared
+    for name in state.declared_containers.keys() {
+        if declared_containers.contains_key(name) {
+            continue;
+        }
+        log::info!(
+            "Removing container no longer declared in lucky.yaml: {}",
+            name
+        );
+        let container = if name == "default" {
+            state.default_container.as_mut()
+        } else {
+            state.named_containers.get_mut(name)
+        };
+        if let Some(container) = container {
+            container.update(|c| c.pending_removal = true);
+        }
+    }
+
+    state.declared_containers = declared_containers;
+
+    // Close the ports of any containers that were removed
+    sync_exposed_ports(state)?;
+
+    Ok(())
+}
+
+/// Apply the changes between the `previous` and current declaration of a container to its config
+fn apply_container_definition(
+    name: &str,
+    container: &mut Option<Cd<ContainerInfo>>,
+    definition: &ContainerDefinition,
+    previous: Option<&ContainerDefinition>,
+) {
+    // Create the container if it doesn't exist yet and apply the whole declaration
+    let previous = match container {
+        Some(_) => previous,
+        None => {
+            log::debug!("Creating declared container: {}", name);
+            *container = Some(Cd::new(ContainerInfo::new(&definition.image)));
+            None
+        }
+    };
+    let container = container.as_mut().expect("Container should exist");
+    let is_new = previous.is_none();
+    let empty = ContainerDefinition::default();
+    let previous = previous.unwrap_or(&empty);
+
+    container.update(|c| {
+        if is_new || previous.image != definition.image {
+            c.config.image = definition.image.clone();
+            c.image_resource = None;
+        }
+
+        for key in previous.env.keys() {
+            if !definition.env.contains_key(key) {
+                c.config.env_vars.remove(key);
+            }
+        }
+        for (key, value) in &definition.env {
+            if previous.env.get(key) != Some(value) {
+                c.config.env_vars.insert(key.clone(), value.clone());
+            }
+        }
+
+        // Volumes were validated when the declaration was interpolated
+        for volume in &previous.volumes {
+            if !definition.volumes.contains(volume) {
+                if let Ok((target, _)) = parse_volume(volume) {
+                    c.config.volumes.remove(&target);
+                }
+            }
+        }
+        for volume in &definition.volumes {
+            if !previous.volumes.contains(volume) {
+                if let Ok((target, mount)) = parse_volume(volume) {
+                    c.config.volumes.insert(target, mount);
+                }
+            }
+        }
+
+        // Ports were validated when the declaration was interpolated
+        for port in &previous.ports {
+            if !definition.ports.contains(port) {
+                if let Ok(binding) = port.parse::<PortBinding>() {
+                    c.config.ports.remove(&binding);
+                }
+            }
+        }
+        for port in &definition.ports {
+            if !previous.ports.contains(port) {
+                if let Ok(binding) = port.parse::<PortBinding>() {
+                    c.config.ports.insert(binding);
+                }
+            }
+        }
+
+        if is_new || previous.network != definition.network {
+            c.config.network = definition.network.clone();
+        }
+        if is_new || previous.command != definition.command {
+            c.config.command = definition.command.clone();
+        }
+    });
+}
+
+/// Compare the desired container configuration with the actual Docker state and re-create any
+/// containers that are missing or have drifted from their configuration
+pub(super) fn reconcile_containers(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    log::debug!("Reconciling containers with Docker");
+
+    // Mark any drifted containers as dirty. This is scoped so that the state is unlocked before
+    // applying container updates.
+    let mut drifted = false;
+    {
+        let mut state = daemon.state.write().unwrap();
+        let state = &mut *state;
+        let runtime = daemon.get_runtime()?;
+
+        let all_containers = state
+            .named_containers
+            .iter_mut()
+            .map(|(name, container)| (name.as_str(), container))
+            .chain(
+                state
+                    .default_container
+                    .iter_mut()
+                    .map(|container| ("default", container)),
+            );
+        for (name, container) in all_containers {
+            // Skip containers that are going to be removed anyway, and containers that are
+            // waiting for updates to be applied because their config doesn't match the container
+            // yet and they will be re-created when the updates are applied
+            if container.pending_removal || !container.is_clean() {
+                continue;
+            }
+
+            let id = match &container.id {
+                Some(id) => id.clone(),
+                None => {
+                    // A container without an ID that isn't waiting for updates to be applied
+                    // failed to be created the last time updates were applied
+                    log::warn!(r#"Container "{}" was never created, creating it"#, name);
+                    container.mark_dirty();
+                    drifted = true;
+                    continue;
+                }
+            };
+
+            match runtime.inspect_container(&id)? {
+                Some(details) => {
+                    // Containers created by older versions of Lucky don't have the config hash
+                    let created_hash = details
+                        .config
+                        .labels
+                        .as_ref()
+                        .and_then(|labels| labels.get(CONFIG_HASH_LABEL));
+                    let config_hash = container.config.options_hash(
+                        &daemon.charm_dir,
+                        &daemon.lucky_data_dir,
+                        &daemon.socket_path,
+                    )?;
+
+                    // Check whether the container still matches its config. Containers that
+                    // Docker is restarting after they exited are left alone so that Docker's
+                    // restart backoff isn't cut short.
+                    let drift = if details.config.image != container.config.image {
+                        Some(format!(
+                            "image is {} instead of {}",
+                            details.config.image, container.config.image
+                        ))
+                    } else if created_hash.map_or(false, |hash| hash != &config_hash) {
+                        Some("container was created with a different configuration".into())
+                    } else if !details.state.running
+                        && !details.state.restarting
+                        && (container.config.restart_policy == RestartPolicy::Always
+                            || container.config.restart_policy == RestartPolicy::UnlessStopped)
+                    {
+                        Some(format!(
+                            "container is not running (exit code {})",
+                            details.state.exit_code
+                        ))
+                    } else {
+                        None
+                    };
+
+                    if let Some(drift) = drift {
+                        log::warn!(
+                            r#"Container "{}" has drifted from its configuration, re-creating it: {}"#,
+                            name,
+                            drift
+                        );
+                        container.mark_dirty();
+                        drifted = true;
+                    }
+                }
+                // If the container doesn't exist anymore
+                None => {
+                    log::warn!(
+                        r#"Container "{}" has been removed from Docker, re-creating it: {}"#,
+                        name,
+                        id
+                    );
+                    container.update(|c| c.id = None);
+                    container.mark_dirty();
+                    drifted = true;
+                }
+            }
+        }
+    }
+
+    // Re-create any drifted containers
+    if drifted {
+        apply_container_updates(daemon)?;
+    }
+
+    Ok(())
+}
+
+/// Pull the latest version of the images of the containers matching `filter` and mark any
+/// containers that are running an older version of their image as dirty
+///
+/// Containers that haven't been created yet, that use an image loaded from a Juju resource, or
+/// that have the `never` pull policy are skipped. Returns the names of the containers that were
+/// marked dirty, with `None` being the default container.
+fn mark_outdated_images<F>(daemon: &LuckyDaemon, filter: F) -> anyhow::Result<Vec<Option<String>>>
+where
+    F: Fn(Option<&str>, &ContainerInfo) -> bool,
+{
+    // Find the containers to check. This is scoped so that the state isn't locked while the images
+    // are pulled, which can take a long time.
+    let to_check = {
+        let state = daemon.state.read().unwrap();
+        let mut to_check = vec![];
+        for (name, container) in state
+            .named_containers
+            .iter()
+            .map(|(name, container)| (Some(name.clone()), container))
+            .chain(
+                state
+                    .default_container
+                    .iter()
+                    .map(|container| (None, container)),
+            )
+        {
+            if container.pending_removal
+                || container.image_resource.is_some()
+                || container.pull_policy == PullPolicy::Never
+                || !filter(name.as_deref(), container)
+            {
+                continue;
+            }
+
+            // Containers that haven't been created will get the latest image when they are
+            let id = match &container.id {
+                Some(id) => id.clone(),
+                None => continue,
+            };
+
+            let registry_auth = match &container.registry_credentials {
+                Some(credentials) => Some(resolve_registry_auth(&state, credentials)?),
+                None => None,
+            };
+            to_check.push((name, id, container.config.image.clone(), registry_auth));
+        }
+
+        to_check
+    };
+
+    // Pull the latest version of the images and compare them with the images that the containers
+    // were created from
+    let mut outdated = vec![];
+    {
+        let runtime = daemon.get_runtime()?;
+        for (name, id, image, registry_auth) in to_check {
+            pull_image(runtime, &image, registry_auth)?;
+
+            let image_id = get_image_id(runtime, &image)?;
+            let details = runtime
+                .inspect_container(&id)?
+                .ok_or_else(|| format_err!("Container not found: {}", id))?;
+            if image_id.as_deref() != Some(details.image.as_str()) {
+                log::info!(
+                    "Container{} is running an outdated image: {}",
+                    name.as_ref().map_or("".into(), |x| format!("[{}]", x)),
+                    image
+                );
+                outdated.push((name, id, image));
+            }
+        }
+    }
+
+    // Mark the outdated containers as dirty so that they will be re-created. Containers that were
+    // re-created or given a different image while the images were being pulled are skipped.
+    let mut state = daemon.state.write().unwrap();
+    let mut marked = vec![];
+    for (name, id, image) in outdated {
+        let container = match &name {
+            Some(name) => state.named_containers.get_mut(name),
+            None => state.default_container.as_mut(),
+        };
+        if let Some(container) = container {
+            if container.id.as_ref() == Some(&id) && container.config.image == image {
+                container.mark_dirty();
+                marked.push(name);
+            }
+        }
+    }
+
+    Ok(marked)
+}
+
+/// Check the registry for newer versions of the images of the containers that have image update
+/// checks enabled and re-create any containers that are out of date
+pub(super) fn check_image_updates(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    log::debug!("Checking for container image updates");
+
+    let outdated = mark_outdated_images(daemon, |_, container| container.check_image_updates)?;
+    if !outdated.is_empty() {
+        apply_container_updates(daemon)?;
+    }
+
+    Ok(())
+}
+
+/// Pull the latest version of a container's image and re-create the container if it is out of
+/// date, returning whether or not the container was upgraded
+pub(super) fn upgrade_container_image(
+    daemon: &LuckyDaemon,
+    container_name: Option<&str>,
+) -> anyhow::Result<bool> {
+    let container_log_name = container_name.map_or("".into(), |x| format!("[{}]", x));
+
+    // Make sure that the container's image can be upgraded. This is scoped so that the state is
+    // unlocked before checking for updates.
+    {
+        let state = daemon.state.read().unwrap();
+        let container = match container_name {
+            Some(name) => state.named_containers.get(name),
+            None => state.default_container.as_ref(),
+        }
+        .ok_or_else(|| format_err!("Container{} does not exist", container_log_name))?;
+
+        if container.image_resource.is_some() {
+            bail!(
+                "Container{} image is loaded from a Juju resource and can't be upgraded by \
+                pulling it. Attach a new revision of the resource instead.",
+                container_log_name
+            );
+        }
+        if container.pull_policy == PullPolicy::Never {
+            bail!(
+                "Container{} has the `never` pull policy so its image can't be upgraded",
+                container_log_name
+            );
+        }
+    }
+
+    log::debug!("Upgrading container image{}", container_log_name);
+    let outdated = mark_outdated_images(daemon, |name, _| name == container_name)?;
+    if outdated.is_empty() {
+        log::debug!("Container image is up to date{}", container_log_name);
+        return Ok(false);
+    }
+
+    apply_container_updates(daemon)?;
+
+    Ok(true)
+}
+
 #[function_name::named]
 /// Apply any updates to container configuration for the charm by running
 pub(super) fn apply_container_updates(daemon: &LuckyDaemon) -> anyhow::Result<()> {
@@ -573,55 +1189,480 @@ pub(super) fn apply_container_updates(daemon: &LuckyDaemon) -> anyhow::Result<()
         "Applying Docker configuration updates"
     );
 
-    // Apply changes for any updated named containers
-    for mut container in state.named_containers.values_mut() {
-        apply_updates(daemon, &mut container)?;
+    // Make sure that none of the containers would bind the same host ports
+    check_port_conflicts(&state)?;
+
+    // Resolve the registry credentials for the containers. This must be done before the
+    // containers are borrowed mutably below.
+    let mut registry_auths = HashMap::new();
+    for (name, container) in state
+        .named_containers
+        .iter()
+        .map(|(name, container)| (Some(name.clone()), container))
+        .chain(
+            state
+                .default_container
+                .iter()
+                .map(|container| (None, container)),
+        )
+    {
+        if let Some(credentials) = &container.registry_credentials {
+            registry_auths.insert(name, resolve_registry_auth(&state, credentials)?);
+        }
     }
 
-    // Remove named containers that are pending removal
+    // Apply changes for any updated named containers, making sure that containers are started
+    // after the containers they depend on, and then for the default container. The state lock is
+    // released while waiting for containers to become healthy so that the daemon can keep checking
+    // container health and serving other requests in the meantime.
+    let names = container_apply_order(&state.named_containers)?
+        .into_iter()
+        .map(Some)
+        .chain(std::iter::once(None));
+    for name in names {
+        let display_name = name.as_deref().unwrap_or("default");
+        let container = match &name {
+            Some(name) => state.named_containers.get(name),
+            None => state.default_container.as_ref(),
+        };
+        let container = match container {
+            Some(container) if !container.is_clean() => container,
+            _ => continue,
+        };
+
+        // Wait for the dependencies that must be healthy before the container is started
+        let dependency_checks = if container.pending_removal {
+            vec![]
+        } else {
+            dependency_health_checks(&state.named_containers, display_name, container)?
+        };
+        if !dependency_checks.is_empty() {
+            drop(state);
+            for (dependency, dependency_id, healthcheck) in &dependency_checks {
+                log::debug!(
+                    r#"Waiting for container "{}" to become healthy before starting "{}""#,
+                    dependency,
+                    display_name
+                );
+                wait_for_healthy(daemon.get_runtime()?, dependency_id, healthcheck)?;
+            }
+            state = daemon.state.write().unwrap();
+        }
+
+        let container = match &name {
+            Some(name) => state.named_containers.get_mut(name),
+            None => state.default_container.as_mut(),
+        };
+        let container = match container {
+            Some(container) => container,
+            None => continue,
+        };
+        let registry_auth = registry_auths.remove(&name);
+
+        // Replace the running container without downtime if the update strategy allows it. This
+        // waits for the new container to become healthy so it is done without the state lock.
+        if let Some(old_id) = start_first_replacement(container) {
+            let container_info = container.clone();
+            drop(state);
+            let new_id =
+                replace_container_start_first(daemon, &container_info, &old_id, registry_auth)?;
+            state = daemon.state.write().unwrap();
+
+            // Mark container_info as "clean" and up-to-date with the system config
+            let container = match &name {
+                Some(name) => state.named_containers.get_mut(name),
+                None => state.default_container.as_mut(),
+            };
+            if let Some(container) = container {
+                container.update(|info| info.id = Some(new_id));
+                container.clean();
+            }
+        } else {
+            apply_updates(daemon, container, registry_auth)?;
+        }
+    }
+
+    // Remove containers that are pending removal
     state
         .named_containers
         .retain(|_name, container| !container.pending_removal);
+    if state
+        .default_container
+        .as_ref()
+        .map_or(false, |container| container.pending_removal)
+    {
+        state.default_container = None;
+    }
 
-    // Apply changes for the default container
-    if let Some(container) = &mut state.default_container {
-        apply_updates(daemon, container)?;
+    // Clean up any networks that aren't needed anymore
+    let runtime = daemon.get_runtime()?;
+    remove_unused_networks(runtime, &state, false)?;
 
-        // Remove container if pending removal
-        if container.pending_removal {
-            state.default_container = None;
+    // Delete any Docker volumes that were removed with their data
+    remove_pending_volumes(runtime, &mut state);
+
+    daemon_set_status!(&mut state, ScriptState::Active);
+    Ok(())
+}
+
+/// Make sure that no two of the unit's containers bind the same host ports
+fn check_port_conflicts(state: &DaemonState) -> anyhow::Result<()> {
+    let bindings: Vec<(&str, &PortBinding)> = state
+        .named_containers
+        .iter()
+        .map(|(name, container)| (name.as_str(), container))
+        .chain(state.default_container.iter().map(|c| ("default", c)))
+        .filter(|(_, container)| !container.pending_removal)
+        .flat_map(|(name, container)| container.config.ports.iter().map(move |b| (name, b)))
+        .collect();
+
+    for (i, (name, binding)) in bindings.iter().enumerate() {
+        for (other_name, other_binding) in &bindings[i + 1..] {
+            if name != other_name && binding.host_ports_overlap(other_binding) {
+                bail!(
+                    "Port binding `{}` of container `{}` conflicts with port binding `{}` of container `{}`",
+                    binding,
+                    name,
+                    other_binding,
+                    other_name
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the order to apply updates to the named containers in so that every container comes after
+/// the containers that it depends on
+///
+/// Returns an error if a container depends on a container that doesn't exist or if there is a
+/// dependency cycle.
+fn container_apply_order(
+    containers: &HashMap<String, Cd<ContainerInfo>>,
+) -> anyhow::Result<Vec<String>> {
+    /// Add a container to the order after its dependencies using a depth-first search. `path` is
+    /// the chain of dependents that led to this container and is used to detect cycles.
+    fn visit(
+        name: &str,
+        containers: &HashMap<String, Cd<ContainerInfo>>,
+        path: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        if order.iter().any(|x| x == name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|x| x == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name.into());
+            bail!("Container dependency cycle: {}", cycle.join(" -> "));
+        }
+
+        path.push(name.into());
+        for dependency in &containers[name].config.depends_on {
+            if !containers.contains_key(&dependency.container) {
+                bail!(
+                    r#"Container "{}" depends on container "{}", which does not exist"#,
+                    name,
+                    dependency.container
+                );
+            }
+            visit(&dependency.container, containers, path, order)?;
+        }
+        path.pop();
+
+        order.push(name.into());
+        Ok(())
+    }
+
+    // Sort the names so that containers without dependencies between them are always applied in
+    // the same order
+    let mut names: Vec<&String> = containers.keys().collect();
+    names.sort();
+
+    let mut order = Vec::with_capacity(containers.len());
+    for name in names {
+        visit(name, containers, &mut vec![], &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Get the ids and health checks of the containers that a container depends on being healthy
+///
+/// The dependencies will already have been started because containers are applied in dependency
+/// order, so only the dependencies that must be healthy have to be waited for before the container
+/// is created. The returned items are the dependency name, its container id, and its health check.
+fn dependency_health_checks(
+    containers: &HashMap<String, Cd<ContainerInfo>>,
+    name: &str,
+    container_info: &ContainerInfo,
+) -> anyhow::Result<Vec<(String, String, HealthCheck)>> {
+    let mut checks = vec![];
+    for dependency in &container_info.config.depends_on {
+        let dependency_info = containers
+            .get(&dependency.container)
+            .filter(|c| !c.pending_removal)
+            .ok_or_else(|| {
+                format_err!(
+                    r#"Container "{}" depends on container "{}", which does not exist"#,
+                    name,
+                    dependency.container
+                )
+            })?;
+        let dependency_id = dependency_info.id.as_ref().ok_or_else(|| {
+            format_err!(
+                r#"Container "{}" depends on container "{}", which has not been started"#,
+                name,
+                dependency.container
+            )
+        })?;
+
+        if dependency.condition == DependencyCondition::Healthy {
+            let healthcheck = dependency_info.config.healthcheck.as_ref().ok_or_else(|| {
+                format_err!(
+                    r#"Container "{}" depends on container "{}" being healthy, but it doesn't have a health check"#,
+                    name,
+                    dependency.container
+                )
+            })?;
+
+            checks.push((
+                dependency.container.clone(),
+                dependency_id.clone(),
+                healthcheck.clone(),
+            ));
+        }
+    }
+
+    Ok(checks)
+}
+
+/// Remove the managed networks that belong to this unit and aren't used by any of its containers
+///
+/// If `remove_all` is true every managed network that belongs to the unit is removed. Networks
+/// shared by the whole charm are only removed when no other unit of the charm has containers on
+/// the host, because the containers of the other units may be connected to them.
+pub(super) fn remove_unused_networks(
+    runtime: &dyn ContainerRuntime,
+    state: &DaemonState,
+    remove_all: bool,
+) -> anyhow::Result<()> {
+    // Get the Docker names of the networks that are still in use
+    let used_networks: HashSet<String> = if remove_all {
+        HashSet::new()
+    } else {
+        state
+            .named_containers
+            .values()
+            .chain(state.default_container.iter())
+            .filter(|container| !container.pending_removal)
+            .flat_map(|container| {
+                container
+                    .config
+                    .networks
+                    .iter()
+                    .map(|(name, attachment)| attachment.scope.docker_network_name(name))
+            })
+            .collect()
+    };
+
+    // Whether charm networks are in use by other units. This is only checked if there is a charm
+    // network to remove.
+    let mut charm_networks_in_use = None;
+
+    for (network, scope) in list_managed_networks(runtime)? {
+        if used_networks.contains(&network) {
+            continue;
+        }
+
+        if scope == NetworkScope::Charm {
+            if charm_networks_in_use.is_none() {
+                charm_networks_in_use = Some(other_units_have_containers(runtime)?);
+            }
+            if charm_networks_in_use == Some(true) {
+                log::debug!(
+                    "Not removing network {} because other units of the charm have containers",
+                    network
+                );
+                continue;
+            }
+        }
+
+        log::debug!("Removing unused network: {}", network);
+        if let Err(e) = runtime.remove_network(&network) {
+            log::warn!("Could not remove network {}: {:?}", network, e);
         }
     }
 
-    daemon_set_status!(&mut state, ScriptState::Active);
     Ok(())
 }
 
+/// Open the Juju ports for the exposed port bindings of the unit's containers and close the ports
+/// that were opened for bindings that have since been removed
+///
+/// Only ports that the daemon opened itself are ever closed.
+pub(super) fn sync_exposed_ports(state: &mut DaemonState) -> anyhow::Result<()> {
+    let exposed_ports: HashSet<String> = state
+        .named_containers
+        .values()
+        .chain(state.default_container.iter())
+        .filter(|container| !container.pending_removal)
+        .flat_map(|container| {
+            container
+                .exposed_ports
+                .iter()
+                .filter(move |binding| container.config.ports.contains(binding))
+                .map(PortBinding::juju_port)
+        })
+        .collect();
+
+    // Record each port as soon as it has been opened or closed so that a failure part of the way
+    // through doesn't make the daemon forget about ports that it opened
+    let to_close: Vec<String> = state
+        .opened_ports
+        .difference(&exposed_ports)
+        .cloned()
+        .collect();
+    for port in to_close {
+        log::debug!("Closing exposed port: {}", port);
+        juju::close_port(&port)?;
+        state.opened_ports.remove(&port);
+    }
+    let to_open: Vec<String> = exposed_ports
+        .difference(&state.opened_ports)
+        .cloned()
+        .collect();
+    for port in to_open {
+        log::debug!("Opening exposed port: {}", port);
+        juju::open_port(&port)?;
+        state.opened_ports.insert(port);
+    }
+
+    Ok(())
+}
+
+/// Delete the Docker managed volumes that were removed from a container along with their data
+///
+/// Volumes that are still used by one of the unit's containers are kept.
+fn remove_pending_volumes(runtime: &dyn ContainerRuntime, state: &mut DaemonState) {
+    let used_volumes: HashSet<String> = state
+        .named_containers
+        .values()
+        .chain(state.default_container.iter())
+        .flat_map(|container| {
+            container
+                .config
+                .volumes
+                .values()
+                .filter(|mount| mount.mount_type == MountType::Volume)
+                .map(|mount| (*mount.source).clone())
+        })
+        .collect();
+
+    for volume in std::mem::replace(&mut state.pending_volume_removals, vec![]) {
+        if used_volumes.contains(&volume) {
+            continue;
+        }
+
+        log::debug!("Removing volume: {}", volume);
+        if let Err(e) = runtime.remove_volume(&volume) {
+            log::warn!("Could not remove volume {}: {:?}", volume, e);
+        }
+    }
+}
+
+/// Get the value of a registry credential from the charm config or key-value store
+fn resolve_credential(state: &DaemonState, source: &CredentialSource) -> anyhow::Result<String> {
+    match source {
+        CredentialSource::Config(key) => state
+            .charm_config
+            .get(key)
+            .map(|value| match &**value {
+                JsonValue::String(s) => s.clone(),
+                value => value.to_string(),
+            })
+            .ok_or_else(|| format_err!("Registry credential config option is not set: {}", key)),
+        CredentialSource::Kv(key) => state
+            .kv
+            .get(key)
+            .map(|value| (**value).clone())
+            .ok_or_else(|| format_err!("Registry credential key-value is not set: {}", key)),
+    }
+}
+
+/// Build the registry auth for pulling an image from the container's registry credentials
+fn resolve_registry_auth(
+    state: &DaemonState,
+    credentials: &RegistryCredentials,
+) -> anyhow::Result<RegistryAuth> {
+    let mut auth = RegistryAuth::builder();
+    auth.username(resolve_credential(state, &credentials.username)?)
+        .password(resolve_credential(state, &credentials.password)?);
+    if let Some(server) = &credentials.server {
+        auth.server_address(server);
+    }
+
+    Ok(auth.build())
+}
+
+/// Returns `true` if the given key-value store key or config option is used as a source for
+/// registry credentials
+pub(super) fn is_credential_source(state: &DaemonState, source: &CredentialSource) -> bool {
+    state
+        .named_containers
+        .values()
+        .chain(state.default_container.iter())
+        .filter_map(|container| container.registry_credentials.as_ref())
+        .any(|credentials| &credentials.username == source || &credentials.password == source)
+}
+
 fn apply_updates(
     daemon: &LuckyDaemon,
     container_info: &mut Cd<ContainerInfo>,
+    registry_auth: Option<RegistryAuth>,
 ) -> anyhow::Result<()> {
     // Skip apply if container config is unchanged since last apply
     if container_info.is_clean() {
         return Ok(());
     }
 
-    // Get the docker connection
-    let docker_conn = daemon.get_docker_conn()?;
-    let docker_conn = docker_conn.lock().unwrap();
-    let containers = docker_conn.containers();
-    let images = docker_conn.images();
+    // If only the resource limits have changed, update them without re-creating the container
+    if let Some(id) = &container_info.id {
+        if only_resources_changed(container_info) {
+            log::debug!("Updating container resource limits: {}", id);
+            match update_container_resources(
+                daemon.get_runtime()?,
+                id,
+                &container_info.config.resources,
+            ) {
+                Ok(()) => {
+                    // Mark container_info as "clean" and up-to-date with the system config
+                    container_info.clean();
+                    return Ok(());
+                }
+                // Docker refuses some updates, such as raising the memory limit above an
+                // already set memory swap limit, so fall back to re-creating the container
+                Err(e) => log::warn!(
+                    "Could not update container resource limits, re-creating container: {:?}",
+                    e
+                ),
+            }
+        }
+    }
+
+    // Get the container runtime
+    let runtime = daemon.get_runtime()?;
 
     // If the container has already been deployed
     if let Some(id) = &container_info.id {
         // Remove the container
-        let container = containers.get(&id);
-
         // TODO: handle NOT MODIFIED error response
+        // The running container was created from the original config
         log::debug!("Stopping container: {}", id);
-        block_on(container.stop(Some(Duration::from_secs(10))))?;
+        runtime.stop_container(id, container_info.original().config.stop_timeout())?;
         log::debug!("Removing container: {}", id);
-        block_on(container.delete())?;
+        runtime.remove_container(id)?;
 
         // Clear the containers ID
         container_info.update(|info| info.id = None);
@@ -629,41 +1670,511 @@ fn apply_updates(
 
     // If this contianer was not meant to be removed
     if !container_info.pending_removal {
-        let mut image_name = container_info.config.image.clone();
+        ensure_image(runtime, container_info, registry_auth)?;
+
+        // Create and start the container
+        let id = create_and_start_container(daemon, runtime, container_info)?;
+
+        // Mark container_info as "clean" and up-to-date with the system config
+        container_info.update(|info| info.id = Some(id));
+        container_info.clean();
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the resource limits are the only part of a container's configuration that
+/// changed and they can be updated without re-creating the container
+fn only_resources_changed(container_info: &Cd<ContainerInfo>) -> bool {
+    let original = container_info.original();
+
+    // Check whether or not anything other than the resource limits has changed
+    let mut unchanged_info = (**container_info).clone();
+    unchanged_info.config.resources = original.config.resources.clone();
+
+    !container_info.is_marked_dirty()
+        && unchanged_info == *original
+        && container_info
+            .config
+            .resources
+            .can_update_from(&original.config.resources)
+}
+
+/// Get the id of the container to replace if a container's updates should be applied with the
+/// `start-first` update strategy
+fn start_first_replacement(container_info: &Cd<ContainerInfo>) -> Option<String> {
+    if container_info.is_clean()
+        || container_info.pending_removal
+        || container_info.config.update_strategy != UpdateStrategy::StartFirst
+        || only_resources_changed(container_info)
+    {
+        return None;
+    }
+
+    container_info.id.clone()
+}
+
+/// Replace a running container using the `start-first` update strategy, returning the id of the
+/// new container
+///
+/// The image is pulled before touching the old container. If the new container doesn't need any
+/// of the host ports used by the old one it is started and health-checked alongside the old
+/// container, otherwise the old container is stopped first. If the new container fails to start
+/// it is removed and the old container is left, or put back, in place.
+fn replace_container_start_first(
+    daemon: &LuckyDaemon,
+    container_info: &Cd<ContainerInfo>,
+    old_id: &str,
+    registry_auth: Option<RegistryAuth>,
+) -> anyhow::Result<String> {
+    let runtime = daemon.get_runtime()?;
+    let old_stop_timeout = container_info.original().config.stop_timeout();
+
+    // Pull the image up front so that it doesn't count towards any downtime
+    ensure_image(runtime, container_info, registry_auth)?;
+
+    // Stop the old container first if the new one would conflict with its ports
+    let ports_conflict = container_info
+        .config
+        .host_ports_conflict_with(&container_info.original().config);
+    if ports_conflict {
+        log::debug!(
+            "New container conflicts with the ports of the old one, stopping old container: {}",
+            old_id
+        );
+        runtime.stop_container(old_id, old_stop_timeout)?;
+    }
+
+    // Create and start the new container, making sure that it becomes healthy
+    let new_id = match create_and_start_container(daemon, runtime, container_info)
+        .and_then(|id| wait_for_container_start(runtime, &id, container_info).map(|_| id))
+    {
+        Ok(id) => id,
+        Err(e) => {
+            log::error!(
+                "Replacement container failed to start, keeping old container: {:?}",
+                e
+            );
+
+            // Restart the old container if we had to stop it
+            if ports_conflict {
+                log::debug!("Restarting old container: {}", old_id);
+                runtime.start_container(old_id)?;
+            }
 
-        // Add `:latest` if the image name doesn't specify a tag
-        if !image_name.contains(':') {
-            image_name = format!("{}:latest", image_name);
+            return Err(e);
         }
+    };
+
+    // Remove the old container
+    if !ports_conflict {
+        log::debug!("Stopping old container: {}", old_id);
+        runtime.stop_container(old_id, old_stop_timeout)?;
+    }
+    log::debug!("Removing old container: {}", old_id);
+    runtime.remove_container(old_id)?;
 
-        if container_info.pull_image {
-            // Pull the image
-            log::debug!("Pulling container image: {}", image_name);
-            block_on(
-                images
-                    .pull(&PullOptions::builder().image(image_name).build())
-                    .collect(),
-            )?;
+    Ok(new_id)
+}
+
+/// Make sure that the container's image is available, pulling it according to the container's
+/// pull policy
+fn ensure_image(
+    runtime: &dyn ContainerRuntime,
+    container_info: &ContainerInfo,
+    registry_auth: Option<RegistryAuth>,
+) -> anyhow::Result<()> {
+    let image = &container_info.config.image;
+
+    match container_info.pull_policy {
+        PullPolicy::Always => pull_image(runtime, image, registry_auth),
+        PullPolicy::IfNotPresent => {
+            if get_image_id(runtime, image)?.is_some() {
+                log::debug!("Container image already present, not pulling: {}", image);
+                Ok(())
+            } else {
+                pull_image(runtime, image, registry_auth)
+            }
         }
+        PullPolicy::Never => Ok(()),
+    }
+}
 
-        // Create the container
-        let docker_options = container_info.config.to_container_options(
-            &daemon.charm_dir,
-            &daemon.lucky_data_dir,
-            &daemon.socket_path,
-        )?;
-        log::trace!("Creating container with options: {:#?}", docker_options);
-        let create_info = block_on(containers.create(&docker_options))?;
+/// Add the `:latest` tag to an image name if it doesn't specify a tag or digest
+fn full_image_name(image: &str) -> String {
+    // The last path component is checked so that registry ports aren't mistaken for tags
+    let last_component = image.rsplit('/').next().unwrap_or(image);
+    if last_component.contains(':') || last_component.contains('@') {
+        image.to_string()
+    } else {
+        format!("{}:latest", image)
+    }
+}
 
-        // Start the container
-        log::debug!("Starting container: {}", create_info.id);
-        let container = containers.get(&create_info.id);
-        block_on(container.start())?;
+/// Get the id of a local image, returning `None` if the image isn't present on the host
+fn get_image_id(runtime: &dyn ContainerRuntime, image: &str) -> anyhow::Result<Option<String>> {
+    runtime.image_id(&full_image_name(image))
+}
 
-        // Mark container_info as "clean" and up-to-date with the system config
-        container_info.update(|info| info.id = Some(create_info.id));
-        container_info.clean();
+/// Pull a container image, adding the `:latest` tag if the image doesn't specify one
+fn pull_image(
+    runtime: &dyn ContainerRuntime,
+    image: &str,
+    registry_auth: Option<RegistryAuth>,
+) -> anyhow::Result<()> {
+    let image_name = full_image_name(image);
+
+    // Pull the image. The credentials are never logged.
+    if registry_auth.is_some() {
+        log::debug!(
+            "Pulling container image with registry credentials: {}",
+            image_name
+        );
+    } else {
+        log::debug!("Pulling container image: {}", image_name);
+    }
+    runtime.pull_image(&image_name, registry_auth)
+}
+
+/// Create and start a container from the container config, returning the new container's id
+fn create_and_start_container(
+    daemon: &LuckyDaemon,
+    runtime: &dyn ContainerRuntime,
+    container_info: &ContainerInfo,
+) -> anyhow::Result<String> {
+    // Create the container
+    let docker_options = container_info.config.to_container_options(
+        &daemon.charm_dir,
+        &daemon.lucky_data_dir,
+        &daemon.socket_path,
+    )?;
+    log::trace!("Creating container with options: {:#?}", docker_options);
+    let id = runtime.create_container(&docker_options)?;
+
+    // Connect the container to its managed networks, creating them if necessary
+    for (name, attachment) in &container_info.config.networks {
+        log::debug!("Connecting container {} to network: {}", id, name);
+        if let Err(e) = ensure_network(runtime, name, attachment.scope)
+            .and_then(|_| connect_network(runtime, &id, name, attachment))
+        {
+            // Don't leave the container that couldn't be connected behind
+            runtime.remove_container(&id).ok();
+            return Err(e);
+        }
+    }
+
+    // Start the container
+    log::debug!("Starting container: {}", id);
+    if let Err(e) = runtime.start_container(&id) {
+        // Don't leave the container that failed to start behind
+        runtime.remove_container(&id).ok();
+        return Err(e);
+    }
+
+    Ok(id)
+}
+
+/// Wait for a newly started container to become healthy, or to at least still be running if it
+/// doesn't have a health check. If it doesn't, the container will be removed and an error returned.
+fn wait_for_container_start(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    container_info: &ContainerInfo,
+) -> anyhow::Result<()> {
+    let result = if let Some(healthcheck) = &container_info.config.healthcheck {
+        wait_for_healthy(runtime, container_id, healthcheck)
+    } else {
+        // Make sure the container didn't exit immediately after starting
+        std::thread::sleep(Duration::from_secs(1));
+        let details = runtime
+            .inspect_container(container_id)?
+            .ok_or_else(|| format_err!("Container not found: {}", container_id))?;
+        if details.state.running {
+            Ok(())
+        } else {
+            Err(format_err!(
+                "Container exited immediately after starting ({}): {}",
+                details.state.exit_code,
+                container_id
+            ))
+        }
+    };
+
+    // Remove the container if it didn't start successfully
+    if result.is_err() {
+        log::debug!("Removing failed container: {}", container_id);
+        runtime
+            .stop_container(container_id, container_info.config.stop_timeout())
+            .ok();
+        runtime.remove_container(container_id).ok();
+    }
+
+    result
+}
+
+/// Poll a container's health until it is healthy, returning an error if it becomes unhealthy or
+/// doesn't become healthy in the time allowed by its health check
+fn wait_for_healthy(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    healthcheck: &HealthCheck,
+) -> anyhow::Result<()> {
+    log::debug!("Waiting for container to become healthy: {}", container_id);
+    let deadline = std::time::Instant::now() + healthcheck.max_startup_time();
+    loop {
+        match get_container_health(runtime, container_id)? {
+            Some(HealthStatus::Healthy) | None => break Ok(()),
+            Some(HealthStatus::Unhealthy) => {
+                break Err(format_err!("Container became unhealthy: {}", container_id))
+            }
+            Some(HealthStatus::Starting) if std::time::Instant::now() > deadline => {
+                break Err(format_err!(
+                    "Timed out waiting for container to become healthy: {}",
+                    container_id
+                ))
+            }
+            Some(HealthStatus::Starting) => std::thread::sleep(Duration::from_secs(1)),
+        }
+    }
+}
+
+/// A stream of container logs that is read in the background
+///
+/// The background stream is stopped when this is dropped, even if the container isn't writing any
+/// logs.
+pub(super) struct LogStream {
+    /// The log output, or an error message if the logs could not be read
+    pub chunks: crossbeam::channel::Receiver<Result<OutputChunk, String>>,
+    /// Stops the background stream when dropped
+    _stop: futures::sync::oneshot::Sender<()>,
+}
+
+/// Stream the logs of a container
+///
+/// The logs are streamed in the background and sent over the returned stream so that the async
+/// runtime isn't held onto while following the logs. `since` is a Unix timestamp.
+pub(super) fn stream_container_logs(
+    daemon: &LuckyDaemon,
+    container_id: &str,
+    tail: Option<u64>,
+    since: Option<i64>,
+    follow: bool,
+) -> anyhow::Result<LogStream> {
+    let logs = daemon.get_runtime()?.logs(
+        container_id,
+        &LogOptions {
+            follow,
+            tail,
+            since,
+        },
+    )?;
+
+    let (sender, receiver) = unbounded_channel();
+    let error_sender = sender.clone();
+    let logs = logs
+        .for_each(move |chunk| {
+            sender
+                .send(Ok(chunk))
+                // Stop streaming if the receiver has been dropped
+                .map_err(|_| format_err!("Log receiver dropped"))
+        })
+        .map_err(move |e| {
+            error_sender.send(Err(format!("{}", e))).ok();
+        });
+
+    // Stream the logs until they end or the stop sender is dropped
+    let (stop_sender, stop_receiver) = futures::sync::oneshot::channel();
+    spawn(logs.select2(stop_receiver).then(|_| Ok(())));
+
+    Ok(LogStream {
+        chunks: receiver,
+        _stop: stop_sender,
+    })
+}
+
+/// Output from a command executed in a container
+pub(super) enum ExecOutput {
+    /// Output written to the command's stdout
+    Stdout(String),
+    /// Output written to the command's stderr
+    Stderr(String),
+    /// The command exited with the given exit code. This is always the last output.
+    Exit(i32),
+}
+
+/// Execute a command in a running container, optionally writing `stdin` to the command's stdin
+///
+/// The command output is streamed over the returned channel as it is produced.
+pub(super) fn exec_in_container(
+    daemon: &LuckyDaemon,
+    container_id: &str,
+    command: &[String],
+    stdin: Option<String>,
+) -> anyhow::Result<crossbeam::channel::Receiver<anyhow::Result<ExecOutput>>> {
+    log::trace!(
+        "Executing command in container {}: {:?}",
+        container_id,
+        command
+    );
+    let mut exec = daemon
+        .get_runtime()?
+        .exec_attached(container_id, command, stdin)
+        .context("Could not execute command in container")?;
+
+    // Stream the command's output and then its exit code
+    let (sender, receiver) = unbounded_channel();
+    std::thread::spawn(move || {
+        for chunk in &mut exec {
+            let output = match chunk {
+                Ok(chunk) if chunk.stderr => ExecOutput::Stderr(chunk.output),
+                Ok(chunk) => ExecOutput::Stdout(chunk.output),
+                Err(e) => {
+                    sender.send(Err(e.into())).ok();
+                    return;
+                }
+            };
+            sender.send(Ok(output)).ok();
+        }
+
+        sender
+            .send(
+                exec.exit_code()
+                    .map(|code| ExecOutput::Exit(code.try_into().unwrap_or(1))),
+            )
+            .ok();
+    });
+
+    Ok(receiver)
+}
+
+#[function_name::named]
+/// Check the health of any containers that have a health check and reflect it in the daemon status
+pub(super) fn update_container_health_status(daemon: &LuckyDaemon) -> anyhow::Result<()> {
+    log::debug!("Checking container health");
+    let mut state = daemon.state.write().unwrap();
+    let runtime = daemon.get_runtime()?;
+
+    // Collect the names of the containers that are starting or unhealthy
+    let mut starting = vec![];
+    let mut unhealthy = vec![];
+    let containers = state
+        .named_containers
+        .iter()
+        .map(|(name, container)| (name.as_str(), container))
+        .chain(
+            state
+                .default_container
+                .iter()
+                .map(|container| ("default", container)),
+        );
+    for (name, container) in containers {
+        // Skip containers that haven't been started or that don't have a health check
+        let id = match (&container.id, &container.config.healthcheck) {
+            (Some(id), Some(_)) => id,
+            _ => continue,
+        };
+
+        match get_container_health(runtime, id)? {
+            Some(HealthStatus::Starting) => starting.push(name.to_string()),
+            Some(HealthStatus::Unhealthy) => unhealthy.push(name.to_string()),
+            Some(HealthStatus::Healthy) | None => (),
+        }
+    }
+
+    // Set the status based on the container health
+    if !unhealthy.is_empty() {
+        daemon_set_status!(
+            &mut state,
+            ScriptState::Blocked,
+            format!("Unhealthy containers: {}", unhealthy.join(", "))
+        );
+    } else if !starting.is_empty() {
+        daemon_set_status!(
+            &mut state,
+            ScriptState::Waiting,
+            format!(
+                "Waiting for containers to become healthy: {}",
+                starting.join(", ")
+            )
+        );
+    } else {
+        daemon_set_status!(&mut state, ScriptState::Active);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a set of named containers from their names and dependencies
+    fn containers(dependencies: &[(&str, &[&str])]) -> HashMap<String, Cd<ContainerInfo>> {
+        dependencies
+            .iter()
+            .map(|(name, depends_on)| {
+                let mut container = ContainerInfo::new("nginx");
+                container.config.depends_on = depends_on
+                    .iter()
+                    .map(|dependency| dependency.parse().unwrap())
+                    .collect();
+                (name.to_string(), Cd::new(container))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apply_order_puts_dependencies_first() {
+        let order = container_apply_order(&containers(&[
+            ("app", &["db", "cache:healthy"]),
+            ("cache", &[]),
+            ("db", &["volume-init"]),
+            ("volume-init", &[]),
+        ]))
+        .unwrap();
+
+        assert_eq!(order, vec!["volume-init", "db", "cache", "app"]);
+    }
+
+    #[test]
+    fn apply_order_is_sorted_without_dependencies() {
+        let order =
+            container_apply_order(&containers(&[("b", &[]), ("c", &[]), ("a", &[])])).unwrap();
+
+        assert_eq!(order, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn apply_order_detects_cycles() {
+        let error =
+            container_apply_order(&containers(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"])]))
+                .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Container dependency cycle: a -> b -> c -> a"
+        );
+
+        // A container can't depend on itself either
+        assert!(container_apply_order(&containers(&[("a", &["a"])])).is_err());
+    }
+
+    #[test]
+    fn interpolate_missing_keys_as_empty() {
+        let mut state = DaemonState::default();
+        state.kv.insert("password".into(), Cd::new("secret".into()));
+        let mut definition = ContainerDefinition::default();
+        definition.env.insert(
+            "DATABASE_URL".into(),
+            "postgres://app:{{kv.password}}@{{kv.db_host}}/app".into(),
+        );
+
+        let definition = interpolate_container_definition(&state, "app", &definition).unwrap();
+        assert_eq!(definition.env["DATABASE_URL"], "postgres://app:secret@/app");
+    }
+
+    #[test]
+    fn apply_order_rejects_missing_dependencies() {
+        assert!(container_apply_order(&containers(&[("app", &["db"])])).is_err());
+    }
+}
@@ -69,6 +69,17 @@ impl<T: Clone + PartialEq> Cd<T> {
         self.force_dirty = true;
     }
 
+    /// Returns `true` if the type has been marked dirty with `mark_dirty()` since the last run of
+    /// `clean()`
+    pub fn is_marked_dirty(&self) -> bool {
+        self.force_dirty
+    }
+
+    /// Get a reference to the inner type as it was at the last run of `clean()`
+    pub fn original(&self) -> &T {
+        &self.inner
+    }
+
     /// Consumes the `Cd` and converts to the inner type
     pub fn into_inner(self) -> T {
         // Return the latest updated inner type if it exists
@@ -2,20 +2,47 @@
 use anyhow::{bail, Context};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use shiplift::builder::ContainerOptions;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use sha1::{Digest, Sha1};
 use shrinkwraprs::Shrinkwrap;
+use strum_macros::{AsRefStr, EnumString};
 
 use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::os::unix::fs::lchown;
+use std::path::{Component, Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
+use crate::docker::runtime::ContainerRuntime;
 use crate::process::{cmd_exists, run_cmd, run_cmd_with_retries};
+use crate::rpc::ContainerHealthcheck as RpcContainerHealthcheck;
+use crate::types::{DockerDaemonConfig, DockerInstallMethod, DockerSettings};
 
 use crate::VOLUME_DIR;
 
+/// The label added to every container with the name of the Juju unit that the container belongs to
+pub(crate) const UNIT_LABEL: &str = "sh.lucky.unit";
+/// The label added to every container with a hash of the options that the container was created
+/// with, used to detect containers that don't match their configuration
+pub(crate) const CONFIG_HASH_LABEL: &str = "sh.lucky.config-hash";
+/// The label added to every container and managed network with the name of the Juju app that it
+/// belongs to
+pub(crate) const APP_LABEL: &str = "sh.lucky.app";
+/// The label added to managed networks with the name of the network as given by the charm
+pub(crate) const NETWORK_LABEL: &str = "sh.lucky.network";
+
+/// The default number of seconds to wait for a container to stop before killing it
+pub(crate) const DEFAULT_STOP_TIMEOUT_SECS: u64 = 10;
+
+// A client for the Docker-compatible container API
+mod api;
+// Container runtime backends
+pub(crate) mod runtime;
+
 /// A struct made of a container definition and the container id
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub(crate) struct ContainerInfo {
@@ -23,8 +50,22 @@ pub(crate) struct ContainerInfo {
     pub id: Option<String>,
     /// Marks this container as pending removal
     pub pending_removal: bool,
-    /// Whether or not to pull the Docker image before running it
-    pub pull_image: bool,
+    /// When to pull the Docker image before running it
+    #[serde(alias = "pull_image", deserialize_with = "deserialize_pull_policy")]
+    pub pull_policy: PullPolicy,
+    /// Whether or not to periodically check the registry for a newer version of the image and
+    /// re-create the container when there is one
+    #[serde(default)]
+    pub check_image_updates: bool,
+    /// The Juju resource that the image was loaded from, if any
+    #[serde(default)]
+    pub image_resource: Option<ImageResource>,
+    /// The credentials used to pull the image from a private registry, if any
+    #[serde(default)]
+    pub registry_credentials: Option<RegistryCredentials>,
+    /// The port bindings that should have their host ports opened in Juju
+    #[serde(default)]
+    pub exposed_ports: HashSet<PortBinding>,
     /// The definition for the desired state of the container. This should match the actual state
     /// of the container if `dirty` is `false`.
     pub config: ContainerConfig,
@@ -35,12 +76,51 @@ impl ContainerInfo {
         ContainerInfo {
             id: None,
             pending_removal: false,
-            pull_image: true,
+            pull_policy: PullPolicy::default(),
+            check_image_updates: false,
+            image_resource: None,
+            registry_credentials: None,
+            exposed_ports: HashSet::new(),
             config: ContainerConfig::new(image),
         }
     }
 }
 
+/// A container image that was loaded from an image tarball attached as a Juju resource
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub(crate) struct ImageResource {
+    /// The name of the Juju resource
+    pub name: String,
+    /// A fingerprint of the resource file that is used to detect when a new revision of the
+    /// resource has been attached
+    pub fingerprint: String,
+}
+
+impl ImageResource {
+    /// Get the fingerprint of a resource file
+    ///
+    /// Juju replaces the resource file when a new revision is attached so the size and
+    /// modification time are enough to tell whether or not it has changed without having to hash
+    /// the whole tarball.
+    pub fn fingerprint_file(path: &Path) -> anyhow::Result<String> {
+        let metadata = fs::metadata(path).context(format!(
+            "Could not read resource file metadata: {}",
+            path.to_string_lossy()
+        ))?;
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Ok(format!(
+            "{}-{}.{}",
+            metadata.len(),
+            modified.as_secs(),
+            modified.subsec_nanos()
+        ))
+    }
+}
+
 #[derive(Shrinkwrap, Serialize, Deserialize, PartialEq, Eq, Hash, Default, Clone, Debug)]
 #[shrinkwrap(mutable)]
 #[serde(transparent)]
@@ -53,19 +133,268 @@ pub struct VolumeSource(pub String);
 /// A volume target path wrapper type to make it more difficult to mix-up sources and targets
 pub struct VolumeTarget(pub String);
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Default, Clone, Debug)]
+/// The kind of mount used for a container volume
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum MountType {
+    /// A host path, or a directory in the unit's volume dir if the source is not absolute
+    Bind,
+    /// A Docker managed named volume
+    Volume,
+    /// An in-memory filesystem that doesn't have a source
+    Tmpfs,
+}
+
+impl Default for MountType {
+    fn default() -> Self {
+        Self::Bind
+    }
+}
+
+/// The mount propagation of a bind mount
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum MountPropagation {
+    Private,
+    Rprivate,
+    Shared,
+    Rshared,
+    Slave,
+    Rslave,
+}
+
+/// The source and options of a container volume
+#[derive(Serialize, Deserialize, PartialEq, Eq, Default, Clone, Debug)]
+pub(crate) struct VolumeMount {
+    /// The kind of mount
+    #[serde(default)]
+    pub mount_type: MountType,
+    /// The host path or volume name. This is empty for tmpfs mounts.
+    pub source: VolumeSource,
+    /// Whether or not the mount is read-only in the container
+    #[serde(default)]
+    pub read_only: bool,
+    /// The mount propagation for bind mounts
+    #[serde(default)]
+    pub propagation: Option<MountPropagation>,
+    /// The size limit of a tmpfs mount in bytes
+    #[serde(default)]
+    pub tmpfs_size: Option<i64>,
+}
+
+impl VolumeMount {
+    /// Create a new mount with the default options
+    pub fn new(mount_type: MountType, source: &str) -> Self {
+        VolumeMount {
+            mount_type,
+            source: VolumeSource(source.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Make sure that the options are valid for the mount type
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self.mount_type {
+            MountType::Bind if self.source.is_empty() => bail!("Bind mounts require a source"),
+            MountType::Volume if self.source.is_empty() || self.source.contains('/') => {
+                bail!("Invalid volume name: {}", &*self.source)
+            }
+            MountType::Tmpfs if !self.source.is_empty() => {
+                bail!("Tmpfs mounts don't have a source")
+            }
+            _ => (),
+        }
+        if self.propagation.is_some() && self.mount_type != MountType::Bind {
+            bail!("Mount propagation can only be set for bind mounts");
+        }
+        if self.tmpfs_size.is_some() && self.mount_type != MountType::Tmpfs {
+            bail!("Size can only be set for tmpfs mounts");
+        }
+
+        Ok(())
+    }
+
+    /// Get the mount options in the format `option1,option2`
+    pub fn options(&self) -> Vec<String> {
+        let mut options = vec![];
+        if self.mount_type != MountType::Bind {
+            options.push(self.mount_type.as_ref().to_string());
+        }
+        if self.read_only {
+            options.push("ro".into());
+        }
+        if let Some(propagation) = self.propagation {
+            options.push(propagation.as_ref().into());
+        }
+        if let Some(size) = self.tmpfs_size {
+            options.push(format!("size={}", size));
+        }
+        options
+    }
+}
+
+/// Parse a volume in the format `source:target[:options]` where the options are a comma
+/// separated list of the mount type ( `bind`, `volume`, or `tmpfs` ), `ro` or `rw`, the mount
+/// propagation, and `size=<bytes>` for tmpfs mounts
+///
+/// The source of a tmpfs mount is ignored.
+pub(crate) fn parse_volume(volume: &str) -> anyhow::Result<(VolumeTarget, VolumeMount)> {
+    let mut parts = volume.splitn(3, ':');
+    let source = parts.next().unwrap_or_default();
+    let target = match parts.next() {
+        Some(target) if target.starts_with('/') => target,
+        _ => bail!(
+            "Could not parse volume, expected `source:target[:options]`: {}",
+            volume
+        ),
+    };
+
+    let mut mount = VolumeMount::new(MountType::Bind, source);
+    for option in parts.next().unwrap_or_default().split(',') {
+        if option.is_empty() || option == "rw" {
+            continue;
+        } else if option == "ro" {
+            mount.read_only = true;
+        } else if option.starts_with("size=") {
+            mount.tmpfs_size = Some(crate::types::parse_byte_size(&option[5..])?);
+        } else if let Ok(mount_type) = option.parse() {
+            mount.mount_type = mount_type;
+        } else if let Ok(propagation) = option.parse() {
+            mount.propagation = Some(propagation);
+        } else {
+            bail!("Invalid volume option `{}`: {}", option, volume);
+        }
+    }
+    if mount.mount_type == MountType::Tmpfs {
+        mount.source = VolumeSource::default();
+    }
+    mount.validate()?;
+
+    Ok((VolumeTarget(target.into()), mount))
+}
+
+/// Deserialize container volumes, also accepting the plain source strings used by older daemon
+/// states
+fn deserialize_volumes<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<VolumeTarget, VolumeMount>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MountOrSource {
+        Mount(VolumeMount),
+        Source(VolumeSource),
+    }
+
+    Ok(
+        HashMap::<VolumeTarget, MountOrSource>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(target, mount)| {
+                let mount = match mount {
+                    MountOrSource::Mount(mount) => mount,
+                    MountOrSource::Source(source) => VolumeMount::new(MountType::Bind, &source),
+                };
+                (target, mount)
+            })
+            .collect(),
+    )
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
 pub struct PortBinding {
+    /// The host IP to bind to. All host IPs are bound if this is not set.
+    #[serde(default)]
+    pub host_ip: Option<String>,
     pub container_port: u32,
     pub host_port: u32,
+    /// The number of consecutive ports, starting at the host and container port, that are bound
+    #[serde(default = "default_port_count")]
+    pub port_count: u32,
     pub protocol: String,
 }
 
+fn default_port_count() -> u32 {
+    1
+}
+
+impl PortBinding {
+    /// Get the bound host ports
+    pub fn host_ports(&self) -> std::ops::RangeInclusive<u32> {
+        self.host_port..=(self.host_port + self.port_count - 1)
+    }
+
+    /// Get the bound container ports
+    pub fn container_ports(&self) -> std::ops::RangeInclusive<u32> {
+        self.container_port..=(self.container_port + self.port_count - 1)
+    }
+
+    /// Get the host ports in the format used by Juju's `open-port` hook tool
+    pub fn juju_port(&self) -> String {
+        if self.port_count == 1 {
+            format!("{}/{}", self.host_port, self.protocol)
+        } else {
+            format!(
+                "{}-{}/{}",
+                self.host_ports().start(),
+                self.host_ports().end(),
+                self.protocol
+            )
+        }
+    }
+
+    /// Returns `true` if this binding and the `other` binding bind any of the same host ports
+    pub fn host_ports_overlap(&self, other: &PortBinding) -> bool {
+        // A binding without a host IP, or with an unspecified one, binds every host IP
+        let binds_all_ips = |ip: &Option<String>| {
+            ip.as_ref().map_or(true, |ip| {
+                ip.parse::<std::net::IpAddr>()
+                    .map_or(false, |ip| ip.is_unspecified())
+            })
+        };
+        let ips_overlap = binds_all_ips(&self.host_ip)
+            || binds_all_ips(&other.host_ip)
+            || self.host_ip == other.host_ip;
+
+        self.protocol == other.protocol
+            && ips_overlap
+            && self.host_ports().start() <= other.host_ports().end()
+            && other.host_ports().start() <= self.host_ports().end()
+    }
+
+    /// Returns `true` if this binding and the `other` binding bind any of the same container ports
+    pub fn container_ports_overlap(&self, other: &PortBinding) -> bool {
+        self.protocol == other.protocol
+            && self.container_ports().start() <= other.container_ports().end()
+            && other.container_ports().start() <= self.container_ports().end()
+    }
+}
+
 impl std::fmt::Display for PortBinding {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_range = |range: std::ops::RangeInclusive<u32>| {
+            if range.start() == range.end() {
+                range.start().to_string()
+            } else {
+                format!("{}-{}", range.start(), range.end())
+            }
+        };
+
+        match &self.host_ip {
+            // IPv6 addresses are put in brackets to separate them from the port
+            Some(ip) if ip.contains(':') => write!(f, "[{}]:", ip)?,
+            Some(ip) => write!(f, "{}:", ip)?,
+            None => (),
+        }
         write!(
             f,
             "{}:{}/{}",
-            self.host_port, self.container_port, self.protocol
+            format_range(self.host_ports()),
+            format_range(self.container_ports()),
+            self.protocol
         )
     }
 }
@@ -73,35 +402,587 @@ impl std::fmt::Display for PortBinding {
 impl FromStr for PortBinding {
     type Err = anyhow::Error;
 
+    /// Parse a port binding in the format `[host_ip:]host_port[-end]:container_port[-end][/proto]`
     fn from_str(port_string: &str) -> Result<Self, Self::Err> {
+        let re = Regex::new(concat!(
+            r"^((?P<host_ip>[0-9.]+|\[[0-9a-fA-F:.]+\]):)?",
+            r"(?P<host_port>[0-9]{1,5})(-(?P<host_port_end>[0-9]{1,5}))?:",
+            r"(?P<container_port>[0-9]{1,5})(-(?P<container_port_end>[0-9]{1,5}))?",
+            r"(/(?P<protocol>(tcp|udp|sctp)))?$",
+        ))
+        .expect("Could not compile regex");
+
+        let captures = match re.captures(port_string) {
+            Some(captures) => captures,
+            None => bail!("Could not parse port binding: {}", port_string),
+        };
+        let port = |name: &str| -> anyhow::Result<Option<u32>> {
+            match captures.name(name) {
+                Some(port) => {
+                    let port: u32 = port.as_str().parse().map_err(|_| {
+                        anyhow::format_err!("Invalid port number: {}", port.as_str())
+                    })?;
+                    if port > 65535 {
+                        bail!("Invalid port number: {}", port);
+                    }
+                    Ok(Some(port))
+                }
+                None => Ok(None),
+            }
+        };
+
+        let host_ip = match captures.name("host_ip") {
+            Some(ip) => {
+                let ip = ip.as_str().trim_start_matches('[').trim_end_matches(']');
+                ip.parse::<std::net::IpAddr>()
+                    .map_err(|_| anyhow::format_err!("Invalid host IP: {}", ip))?;
+                Some(ip.to_string())
+            }
+            None => None,
+        };
+        let host_port = port("host_port")?
+            .ok_or_else(|| anyhow::format_err!("Missing host port: {}", port_string))?;
+        let container_port = port("container_port")?
+            .ok_or_else(|| anyhow::format_err!("Missing container port: {}", port_string))?;
+        let host_port_end = port("host_port_end")?.unwrap_or(host_port);
+        let container_port_end = port("container_port_end")?.unwrap_or(container_port);
+        if host_port_end < host_port || container_port_end < container_port {
+            bail!("Invalid port range: {}", port_string);
+        }
+        if host_port_end - host_port != container_port_end - container_port {
+            bail!(
+                "Host and container port ranges must be the same size: {}",
+                port_string
+            );
+        }
+
+        Ok(PortBinding {
+            host_ip,
+            host_port,
+            container_port,
+            port_count: host_port_end - host_port + 1,
+            protocol: captures
+                .name("protocol")
+                .map_or("tcp".into(), |x| x.as_str().into()),
+        })
+    }
+}
+
+/// The place to get a registry credential from
+///
+/// Credentials are never stored in the container config itself, only where to find them, so that
+/// they don't end up in the daemon state or logs.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CredentialSource {
+    /// A charm config option
+    Config(String),
+    /// A key in the unit's key-value store
+    Kv(String),
+}
+
+impl std::fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(key) => write!(f, "config:{}", key),
+            Self::Kv(key) => write!(f, "kv:{}", key),
+        }
+    }
+}
+
+impl FromStr for CredentialSource {
+    type Err = anyhow::Error;
+
+    /// Parse a credential source in the format `config:key` or `kv:key`
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parts = source.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("config"), Some(key)) if !key.is_empty() => Ok(Self::Config(key.into())),
+            (Some("kv"), Some(key)) if !key.is_empty() => Ok(Self::Kv(key.into())),
+            _ => Err(anyhow::format_err!(
+                "Could not parse credential source, expected `config:<key>` or `kv:<key>`: {}",
+                source
+            )),
+        }
+    }
+}
+
+/// The credentials used to pull a container image from a private registry
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub(crate) struct RegistryCredentials {
+    /// The registry server address. Defaults to Docker Hub if not set.
+    pub server: Option<String>,
+    /// Where to get the registry username
+    pub username: CredentialSource,
+    /// Where to get the registry password
+    pub password: CredentialSource,
+}
+
+/// A host device mapped into a container
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
+pub struct DeviceMapping {
+    pub host_path: String,
+    pub container_path: String,
+    /// The cgroup permissions for the device: any combination of `r`, `w`, and `m`
+    pub permissions: String,
+}
+
+impl std::fmt::Display for DeviceMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.host_path, self.container_path, self.permissions
+        )
+    }
+}
+
+impl FromStr for DeviceMapping {
+    type Err = anyhow::Error;
+
+    /// Parse a device mapping in the same format as the Docker CLI's `--device` flag:
+    /// `host_path[:container_path][:permissions]`
+    fn from_str(device_string: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(
-            r"^(?P<host_port>[0-9]{1,5}):(?P<container_port>[0-9]{1,5})(/(?P<protocol>(tcp|udp)))?$",
-        ).expect("Could not compile regex");
-
-        if let Some(captures) = re.captures(port_string) {
-            Ok(PortBinding {
-                host_port: captures
-                    .name("host_port")
-                    .expect("Expected host port")
-                    .as_str()
-                    .parse()
-                    .expect("Could not parse int"),
-                container_port: captures
-                    .name("container_port")
-                    .expect("expected container port")
-                    .as_str()
-                    .parse()
-                    .expect("Could not parse int"),
-                protocol: captures
-                    .name("protocol")
-                    .map_or("tcp".into(), |x| x.as_str().into()),
+            r"^(?P<host_path>/[^:]+)(:(?P<container_path>/[^:]+))?(:(?P<permissions>[rwm]{1,3}))?$",
+        )
+        .expect("Could not compile regex");
+
+        if let Some(captures) = re.captures(device_string) {
+            let host_path = captures
+                .name("host_path")
+                .expect("Expected host path")
+                .as_str();
+
+            Ok(DeviceMapping {
+                host_path: host_path.into(),
+                container_path: captures
+                    .name("container_path")
+                    .map_or(host_path, |x| x.as_str())
+                    .into(),
+                permissions: captures
+                    .name("permissions")
+                    .map_or("rwm", |x| x.as_str())
+                    .into(),
             })
         } else {
-            Err(anyhow::format_err!("Could not parse port binding"))
+            Err(anyhow::format_err!(
+                "Could not parse device mapping: {}",
+                device_string
+            ))
+        }
+    }
+}
+
+/// What a container that another container depends on has to be doing before the dependent
+/// container is started
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum DependencyCondition {
+    /// The dependency has been started
+    Started,
+    /// The dependency's health check is passing
+    Healthy,
+}
+
+impl Default for DependencyCondition {
+    fn default() -> Self {
+        Self::Started
+    }
+}
+
+/// A named container that another container depends on
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub(crate) struct ContainerDependency {
+    /// The name of the container that is depended on
+    pub container: String,
+    /// What the container has to be doing before the dependent container is started
+    #[serde(default)]
+    pub condition: DependencyCondition,
+}
+
+impl std::fmt::Display for ContainerDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.container, self.condition.as_ref())
+    }
+}
+
+impl FromStr for ContainerDependency {
+    type Err = anyhow::Error;
+
+    /// Parse a dependency in the format `container[:condition]`
+    fn from_str(dependency_string: &str) -> Result<Self, Self::Err> {
+        let mut parts = dependency_string.splitn(2, ':');
+        let container = parts.next().unwrap_or("");
+        if container.is_empty() {
+            bail!(
+                "Could not parse container dependency: {}",
+                dependency_string
+            );
+        }
+
+        let condition = match parts.next() {
+            Some(condition) => condition
+                .parse()
+                .map_err(|_| anyhow::format_err!("Invalid dependency condition: {}", condition))?,
+            None => DependencyCondition::default(),
+        };
+
+        Ok(ContainerDependency {
+            container: container.into(),
+            condition,
+        })
+    }
+}
+
+/// A Docker container restart policy
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum RestartPolicy {
+    /// Never restart the container
+    No,
+    /// Restart the container if it exits non-zero, optionally limiting the number of retries
+    OnFailure { max_retries: Option<u64> },
+    /// Always restart the container, even if it was manually stopped
+    Always,
+    /// Always restart the container unless it was manually stopped
+    UnlessStopped,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::UnlessStopped
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::No => write!(f, "no"),
+            Self::OnFailure { max_retries: None } => write!(f, "on-failure"),
+            Self::OnFailure {
+                max_retries: Some(max_retries),
+            } => write!(f, "on-failure:{}", max_retries),
+            Self::Always => write!(f, "always"),
+            Self::UnlessStopped => write!(f, "unless-stopped"),
+        }
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = anyhow::Error;
+
+    /// Parse a restart policy in the same format as the Docker CLI's `--restart` flag
+    fn from_str(policy_string: &str) -> Result<Self, Self::Err> {
+        let re = Regex::new(
+            r"^(?P<policy>no|on-failure|always|unless-stopped)(:(?P<max_retries>[0-9]+))?$",
+        )
+        .expect("Could not compile regex");
+
+        if let Some(captures) = re.captures(policy_string) {
+            let max_retries = match captures.name("max_retries") {
+                Some(max_retries) => Some(max_retries.as_str().parse().map_err(|_| {
+                    anyhow::format_err!("Invalid maximum retry count: {}", max_retries.as_str())
+                })?),
+                None => None,
+            };
+
+            match captures.name("policy").expect("Expected policy").as_str() {
+                "on-failure" => Ok(Self::OnFailure { max_retries }),
+                // Only the `on-failure` policy accepts a max retry count
+                _ if max_retries.is_some() => Err(anyhow::format_err!(
+                    "Only the `on-failure` restart policy accepts a maximum retry count"
+                )),
+                "no" => Ok(Self::No),
+                "always" => Ok(Self::Always),
+                "unless-stopped" => Ok(Self::UnlessStopped),
+                _ => unreachable!(),
+            }
+        } else {
+            Err(anyhow::format_err!(
+                "Could not parse restart policy: {}",
+                policy_string
+            ))
+        }
+    }
+}
+
+/// Resource limits for a container
+///
+/// Limits that are `None` are not constrained.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Default, Clone, Debug)]
+pub(crate) struct ResourceLimits {
+    /// The memory limit in bytes
+    pub memory: Option<i64>,
+    /// The total memory plus swap limit in bytes. `-1` allows unlimited swap.
+    pub memory_swap: Option<i64>,
+    /// The relative CPU weight of the container compared to other containers
+    pub cpu_shares: Option<i64>,
+    /// The microseconds of CPU time that the container can use every CPU period
+    pub cpu_quota: Option<i64>,
+    /// The length of the CPU period in microseconds
+    pub cpu_period: Option<i64>,
+    /// The maximum number of processes in the container. `-1` is unlimited.
+    pub pids_limit: Option<i64>,
+    /// The size of `/dev/shm` in bytes
+    pub shm_size: Option<i64>,
+}
+
+impl ResourceLimits {
+    /// The names of the resource limits
+    pub const NAMES: &'static [&'static str] = &[
+        "memory",
+        "memory-swap",
+        "cpu-shares",
+        "cpu-quota",
+        "cpu-period",
+        "pids-limit",
+        "shm-size",
+    ];
+
+    /// The names of the resource limits in the Docker API, in the same order as `NAMES`
+    const API_NAMES: &'static [&'static str] = &[
+        "Memory",
+        "MemorySwap",
+        "CpuShares",
+        "CpuQuota",
+        "CpuPeriod",
+        "PidsLimit",
+        "ShmSize",
+    ];
+
+    /// Get a mutable reference to a resource limit by name
+    pub fn get_mut(&mut self, name: &str) -> anyhow::Result<&mut Option<i64>> {
+        Ok(match name {
+            "memory" => &mut self.memory,
+            "memory-swap" => &mut self.memory_swap,
+            "cpu-shares" => &mut self.cpu_shares,
+            "cpu-quota" => &mut self.cpu_quota,
+            "cpu-period" => &mut self.cpu_period,
+            "pids-limit" => &mut self.pids_limit,
+            "shm-size" => &mut self.shm_size,
+            other => bail!("Unrecognized resource limit: {}", other),
+        })
+    }
+
+    /// Get a list of the limits that have been set along with their names
+    pub fn to_vec(&self) -> Vec<(&'static str, i64)> {
+        let limits = [
+            self.memory,
+            self.memory_swap,
+            self.cpu_shares,
+            self.cpu_quota,
+            self.cpu_period,
+            self.pids_limit,
+            self.shm_size,
+        ];
+
+        Self::NAMES
+            .iter()
+            .zip(limits.iter())
+            .filter_map(|(&name, limit)| limit.map(|x| (name, x)))
+            .collect()
+    }
+
+    /// Get the limits that have been set as Docker API host config fields
+    pub fn to_api_fields(&self) -> JsonMap<String, JsonValue> {
+        self.to_vec()
+            .into_iter()
+            .map(|(name, limit)| {
+                let index = Self::NAMES
+                    .iter()
+                    .position(|&x| x == name)
+                    .expect("Unknown resource limit");
+                (Self::API_NAMES[index].to_string(), json!(limit))
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the container can be updated from the `old` limits to these limits
+    /// without having to re-create it
+    ///
+    /// Docker can't change the shared memory size of an existing container and can't reliably
+    /// reset limits that have already been set, so removing a limit requires a re-create.
+    pub fn can_update_from(&self, old: &ResourceLimits) -> bool {
+        let new_limits = self.to_vec();
+
+        self.shm_size == old.shm_size
+            && old
+                .to_vec()
+                .iter()
+                .all(|(name, _)| new_limits.iter().any(|(new_name, _)| new_name == name))
+    }
+}
+
+/// A Docker health check for a container
+#[derive(Serialize, Deserialize, PartialEq, Eq, Default, Clone, Debug)]
+pub(crate) struct HealthCheck {
+    /// The command used to check the container's health. It is run with the container's shell.
+    pub cmd: String,
+    /// The number of seconds to wait between checks
+    pub interval: Option<u64>,
+    /// The number of seconds to wait before considering the check to have hung
+    pub timeout: Option<u64>,
+    /// The number of consecutive failures needed to consider the container unhealthy
+    pub retries: Option<u64>,
+    /// The number of seconds to give the container to start before counting failed checks
+    pub start_period: Option<u64>,
+}
+
+// Implement `from` and `into` for the RPC version of this struct
+impl From<RpcContainerHealthcheck> for HealthCheck {
+    fn from(healthcheck: RpcContainerHealthcheck) -> Self {
+        // Negative durations and retries don't make sense so we ignore them
+        let to_u64 = |x: Option<i64>| x.and_then(|x| x.try_into().ok());
+
+        HealthCheck {
+            cmd: healthcheck.cmd,
+            interval: to_u64(healthcheck.interval),
+            timeout: to_u64(healthcheck.timeout),
+            retries: to_u64(healthcheck.retries),
+            start_period: to_u64(healthcheck.start_period),
+        }
+    }
+}
+impl Into<RpcContainerHealthcheck> for HealthCheck {
+    fn into(self) -> RpcContainerHealthcheck {
+        // Durations and retries will never be large enough to overflow an `i64`
+        let to_i64 = |x: Option<u64>| x.and_then(|x| x.try_into().ok());
+
+        RpcContainerHealthcheck {
+            cmd: self.cmd,
+            interval: to_i64(self.interval),
+            timeout: to_i64(self.timeout),
+            retries: to_i64(self.retries),
+            start_period: to_i64(self.start_period),
+        }
+    }
+}
+
+impl HealthCheck {
+    /// Get the longest amount of time that the container could take to become healthy, using the
+    /// Docker defaults for any unset durations and retries
+    pub fn max_startup_time(&self) -> Duration {
+        let interval = self.interval.unwrap_or(30);
+        let timeout = self.timeout.unwrap_or(30);
+        let retries = self.retries.unwrap_or(3);
+        let start_period = self.start_period.unwrap_or(0);
+
+        Duration::from_secs(start_period + (interval + timeout) * retries)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, AsRefStr, EnumString)]
+#[strum(serialize_all = "snake_case")]
+/// The health status of a container as reported by its Docker health check
+pub(crate) enum HealthStatus {
+    /// The container is still inside of its start period
+    Starting,
+    /// The health check is passing
+    Healthy,
+    /// The health check has failed more than the allowed number of retries
+    Unhealthy,
+}
+
+/// The strategy used to replace a container when its configuration changes
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum UpdateStrategy {
+    /// Stop and remove the old container before creating the new one
+    Recreate,
+    /// Pull the image and start the new container, waiting for it to become healthy, before
+    /// removing the old one
+    StartFirst,
+}
+
+impl Default for UpdateStrategy {
+    fn default() -> Self {
+        Self::Recreate
+    }
+}
+
+/// When to pull a container's image before creating the container
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum PullPolicy {
+    /// Pull the image every time the container is created
+    Always,
+    /// Only pull the image if it isn't already present on the host
+    IfNotPresent,
+    /// Never pull the image
+    Never,
+}
+
+impl Default for PullPolicy {
+    fn default() -> Self {
+        Self::Always
+    }
+}
+
+/// Deserialize a pull policy, also accepting the `pull_image` bool used by older daemon states
+fn deserialize_pull_policy<'de, D>(deserializer: D) -> Result<PullPolicy, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PullPolicyOrBool {
+        Policy(PullPolicy),
+        Bool(bool),
+    }
+
+    Ok(match PullPolicyOrBool::deserialize(deserializer)? {
+        PullPolicyOrBool::Policy(policy) => policy,
+        PullPolicyOrBool::Bool(true) => PullPolicy::Always,
+        PullPolicyOrBool::Bool(false) => PullPolicy::Never,
+    })
+}
+
+/// Which units share a managed network
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab_case")]
+pub(crate) enum NetworkScope {
+    /// The network is only used by the containers of this unit
+    Unit,
+    /// The network is shared by the containers of every unit of the charm on the same machine
+    Charm,
+}
+
+impl Default for NetworkScope {
+    fn default() -> Self {
+        Self::Unit
+    }
+}
+
+impl NetworkScope {
+    /// Get the name of the Docker network for a managed network with the given name
+    pub fn docker_network_name(self, name: &str) -> String {
+        let unit_name = std::env::var("JUJU_UNIT_NAME").unwrap_or_default();
+        match self {
+            NetworkScope::Unit => format!("lucky-{}-{}", unit_name.replace('/', "-"), name),
+            NetworkScope::Charm => format!("lucky-{}-{}", app_name(&unit_name), name),
         }
     }
 }
 
+/// A managed network that a container is connected to
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Clone, Debug)]
+pub(crate) struct NetworkAttachment {
+    /// Which units share the network
+    pub scope: NetworkScope,
+    /// The DNS names that the container can be reached at on the network
+    pub aliases: Vec<String>,
+}
+
+/// Get the name of the Juju app from a unit name
+pub(crate) fn app_name(unit_name: &str) -> &str {
+    unit_name.split('/').next().unwrap_or(unit_name)
+}
+
 /// The container configuration options such as image, volumes, ports, etc.
 #[derive(Serialize, Deserialize, Default, PartialEq, Clone, Debug)]
 pub(crate) struct ContainerConfig {
@@ -109,11 +990,67 @@ pub(crate) struct ContainerConfig {
     pub env_vars: HashMap<String, String>,
     pub entrypoint: Option<String>,
     pub command: Option<Vec<String>>,
-    /// Volume mapping from target to source
-    pub volumes: HashMap<VolumeTarget, VolumeSource>,
+    /// Volume mapping from target to source and mount options
+    #[serde(deserialize_with = "deserialize_volumes")]
+    pub volumes: HashMap<VolumeTarget, VolumeMount>,
     // The port bindings
     pub ports: HashSet<PortBinding>,
     pub network: Option<String>,
+    /// The managed networks that the container is connected to, by network name
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkAttachment>,
+    /// The container health check
+    #[serde(default)]
+    pub healthcheck: Option<HealthCheck>,
+    /// The policy for restarting the container when it exits
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    /// The container resource limits
+    #[serde(default)]
+    pub resources: ResourceLimits,
+    /// The container hostname
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// The container labels
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Extra `/etc/hosts` entries mapping hostname to IP address
+    #[serde(default)]
+    pub extra_hosts: HashMap<String, String>,
+    /// DNS servers for the container to use instead of the host's
+    #[serde(default)]
+    pub dns_servers: Vec<String>,
+    /// DNS search domains for the container to use instead of the host's
+    #[serde(default)]
+    pub dns_search_domains: Vec<String>,
+    /// Whether or not to run the container in privileged mode
+    #[serde(default)]
+    pub privileged: bool,
+    /// Linux capabilities to add to the container
+    #[serde(default)]
+    pub cap_add: Vec<String>,
+    /// Linux capabilities to drop from the container
+    #[serde(default)]
+    pub cap_drop: Vec<String>,
+    /// Host devices to map into the container
+    #[serde(default)]
+    pub devices: Vec<DeviceMapping>,
+    /// Security options such as seccomp and AppArmor profiles, as passed to `--security-opt`
+    #[serde(default)]
+    pub security_opts: Vec<String>,
+    /// The strategy used to replace the container when its configuration changes
+    #[serde(default)]
+    pub update_strategy: UpdateStrategy,
+    /// The named containers that must be started before this container
+    #[serde(default)]
+    pub depends_on: Vec<ContainerDependency>,
+    /// The signal sent to the container to stop it. Docker's default, `SIGTERM`, is used if this is
+    /// not set.
+    #[serde(default)]
+    pub stop_signal: Option<String>,
+    /// The number of seconds to wait for the container to stop before killing it
+    #[serde(default)]
+    pub stop_timeout: Option<u64>,
 }
 
 impl ContainerConfig {
@@ -124,7 +1061,34 @@ impl ContainerConfig {
         }
     }
 
-    /// Get a `ContainerOptions` struct that can be given to shiplift to run the container
+    /// Get the time to wait for the container to stop before killing it
+    pub fn stop_timeout(&self) -> Duration {
+        Duration::from_secs(self.stop_timeout.unwrap_or(DEFAULT_STOP_TIMEOUT_SECS))
+    }
+
+    /// Returns `true` if a container with this config can't run at the same time as a container
+    /// with the `other` config because they would bind the same host ports
+    ///
+    /// Containers on the host network are always considered to conflict because we can't know
+    /// which ports the apps inside of them listen on.
+    pub fn host_ports_conflict_with(&self, other: &ContainerConfig) -> bool {
+        let is_host_network = |config: &ContainerConfig| config.network.as_deref() == Some("host");
+        if is_host_network(self) && is_host_network(other) {
+            return true;
+        }
+
+        self.ports.iter().any(|port| {
+            other
+                .ports
+                .iter()
+                .any(|other| port.host_ports_overlap(other))
+        })
+    }
+
+    /// Get the body of the Docker API request used to create the container
+    ///
+    /// The body is built by hand because shiplift's container options builder doesn't support
+    /// settings such as health checks.
     ///
     /// The `charm_dir` is used as reference when mounting the container scripts into the container
     /// and the `socket_path` is used to mount the Lucky Daemon socket inside the container.
@@ -133,11 +1097,68 @@ impl ContainerConfig {
         charm_dir: &Path,
         lucky_data_dir: &Path,
         socket_path: &Path,
-    ) -> anyhow::Result<ContainerOptions> {
-        let mut options = ContainerOptions::builder(&self.image);
+    ) -> anyhow::Result<JsonValue> {
+        // This is warned about here so that containers from the `lucky.yaml` are warned about as
+        // well as the ones configured by scripts
+        if self.privileged {
+            log::warn!(
+                concat!(
+                    "Container with image {} is run in privileged mode. This gives the container ",
+                    "full access to the host's devices and disables most of its isolation. ",
+                    "Prefer adding only the capabilities and devices that it needs."
+                ),
+                self.image
+            );
+        }
+        if self
+            .cap_add
+            .iter()
+            .any(|cap| cap.eq_ignore_ascii_case("ALL"))
+        {
+            log::warn!(
+                concat!(
+                    "Container with image {} is given all capabilities, which disables most of ",
+                    "its isolation. Prefer adding only the capabilities that it needs."
+                ),
+                self.image
+            );
+        }
+
+        self.build_container_options(charm_dir, lucky_data_dir, socket_path)
+    }
+
+    /// Get the hash of the options that a container with this config is created with
+    ///
+    /// This is compared to the hash that containers are labeled with to find containers that don't
+    /// match their config.
+    pub fn options_hash(
+        &self,
+        charm_dir: &Path,
+        lucky_data_dir: &Path,
+        socket_path: &Path,
+    ) -> anyhow::Result<String> {
+        let options = self.build_container_options(charm_dir, lucky_data_dir, socket_path)?;
+
+        Ok(options["Labels"][CONFIG_HASH_LABEL]
+            .as_str()
+            .expect("Missing config hash label")
+            .into())
+    }
+
+    /// Build the container options without warning about the container's privileges
+    fn build_container_options(
+        &self,
+        charm_dir: &Path,
+        lucky_data_dir: &Path,
+        socket_path: &Path,
+    ) -> anyhow::Result<JsonValue> {
+        let mut options = JsonMap::new();
+        let mut host_config = JsonMap::new();
         let mut volumes: Vec<String> = vec![];
         let mut env: Vec<String> = vec![];
 
+        options.insert("Image".into(), json!(self.image));
+
         // Mount container scripts into the container
         volumes.push(format!(
             "{}:{}",
@@ -173,84 +1194,765 @@ impl ContainerConfig {
 
         // Add entrypoint
         if let Some(entrypoint) = &self.entrypoint {
-            options.entrypoint(&entrypoint);
+            options.insert("Entrypoint".into(), json!(entrypoint));
         }
 
         // Add command
         if let Some(cmd) = &self.command {
-            options.cmd(cmd.iter().map(AsRef::as_ref).collect());
+            options.insert("Cmd".into(), json!(cmd));
         }
 
         // Add other specified volumes
-        for (target, source) in &self.volumes {
-            let host_path = if source.starts_with('/') {
-                PathBuf::from(&**source)
-            } else {
-                lucky_data_dir.join(VOLUME_DIR).join(&**source)
-            };
-
-            // Create the host path
-            if !host_path.exists() {
-                fs::create_dir_all(&host_path).context(format!(
-                    "Could not create dir: {}",
-                    host_path.to_string_lossy()
-                ))?;
+        let mut tmpfs = JsonMap::new();
+        for (target, mount) in &self.volumes {
+            let mut mount_options = vec![];
+            if mount.read_only {
+                mount_options.push("ro".to_string());
             }
+            if let Some(propagation) = mount.propagation {
+                mount_options.push(propagation.as_ref().into());
+            }
+            if let Some(size) = mount.tmpfs_size {
+                mount_options.push(format!("size={}", size));
+            }
+
+            let source = match mount.mount_type {
+                MountType::Bind => {
+                    let host_path = if mount.source.starts_with('/') {
+                        PathBuf::from(&*mount.source)
+                    } else {
+                        lucky_data_dir.join(VOLUME_DIR).join(&*mount.source)
+                    };
+
+                    // Create the host path
+                    if !host_path.exists() {
+                        fs::create_dir_all(&host_path).context(format!(
+                            "Could not create dir: {}",
+                            host_path.to_string_lossy()
+                        ))?;
+                    }
+
+                    host_path.to_string_lossy().into_owned()
+                }
+                MountType::Volume => (*mount.source).clone(),
+                // Tmpfs mounts are not binds and are added separately
+                MountType::Tmpfs => {
+                    tmpfs.insert((**target).clone(), json!(mount_options.join(",")));
+                    continue;
+                }
+            };
 
             // Add volume to container
-            volumes.push(format!("{}:{}", host_path.to_string_lossy(), &**target));
+            if mount_options.is_empty() {
+                volumes.push(format!("{}:{}", source, &**target));
+            } else {
+                volumes.push(format!(
+                    "{}:{}:{}",
+                    source,
+                    &**target,
+                    mount_options.join(",")
+                ));
+            }
+        }
+
+        // Add volumes. These and the other lists built from maps are sorted so that the options,
+        // and their hash, are the same every time they are built from the same config.
+        volumes.sort();
+        host_config.insert("Binds".into(), json!(volumes));
+
+        // Add tmpfs mounts
+        if !tmpfs.is_empty() {
+            host_config.insert("Tmpfs".into(), tmpfs.into());
         }
 
         // Add ports
-        for PortBinding {
-            container_port,
-            protocol,
-            host_port,
-        } in &self.ports
-        {
-            options.expose(*container_port, protocol, *host_port);
+        let mut exposed_ports = JsonMap::new();
+        let mut port_bindings = JsonMap::new();
+        let mut ports: Vec<&PortBinding> = self.ports.iter().collect();
+        ports.sort_by_key(|binding| binding.to_string());
+        for binding in ports {
+            for (container_port, host_port) in binding.container_ports().zip(binding.host_ports()) {
+                let port = format!("{}/{}", container_port, binding.protocol);
+                exposed_ports.insert(port.clone(), json!({}));
+
+                // The same container port can be bound to more than one host IP
+                let host_bindings = port_bindings.entry(port).or_insert_with(|| json!([]));
+                if let JsonValue::Array(host_bindings) = host_bindings {
+                    host_bindings.push(json!({
+                        "HostIp": binding.host_ip.as_deref().unwrap_or(""),
+                        "HostPort": host_port.to_string(),
+                    }));
+                }
+            }
+        }
+        if !exposed_ports.is_empty() {
+            options.insert("ExposedPorts".into(), exposed_ports.into());
+            host_config.insert("PortBindings".into(), port_bindings.into());
         }
 
         // Set network
         if let Some(network) = &self.network {
-            options.network_mode(network);
+            host_config.insert("NetworkMode".into(), json!(network));
+        }
+
+        // Set hostname
+        if let Some(hostname) = &self.hostname {
+            options.insert("Hostname".into(), json!(hostname));
+        }
+
+        // Set the stop signal and timeout
+        if let Some(stop_signal) = &self.stop_signal {
+            options.insert("StopSignal".into(), json!(stop_signal));
+        }
+        if let Some(stop_timeout) = self.stop_timeout {
+            options.insert("StopTimeout".into(), json!(stop_timeout));
+        }
+
+        // Add labels, including the names of the unit and app that the container belongs to
+        let mut labels = self.labels.clone();
+        if let Ok(unit_name) = std::env::var("JUJU_UNIT_NAME") {
+            labels.insert(APP_LABEL.into(), app_name(&unit_name).into());
+            labels.insert(UNIT_LABEL.into(), unit_name);
+        }
+        options.insert("Labels".into(), json!(labels));
+
+        // Add extra hosts
+        if !self.extra_hosts.is_empty() {
+            let mut extra_hosts: Vec<String> = self
+                .extra_hosts
+                .iter()
+                .map(|(hostname, ip)| format!("{}:{}", hostname, ip))
+                .collect();
+            extra_hosts.sort();
+            host_config.insert("ExtraHosts".into(), json!(extra_hosts));
+        }
+
+        // Set DNS servers and search domains
+        if !self.dns_servers.is_empty() {
+            host_config.insert("Dns".into(), json!(self.dns_servers));
+        }
+        if !self.dns_search_domains.is_empty() {
+            host_config.insert("DnsSearch".into(), json!(self.dns_search_domains));
+        }
+
+        // Set privileges and capabilities
+        if self.privileged {
+            host_config.insert("Privileged".into(), json!(true));
+        }
+        if !self.cap_add.is_empty() {
+            host_config.insert("CapAdd".into(), json!(self.cap_add));
+        }
+        if !self.cap_drop.is_empty() {
+            host_config.insert("CapDrop".into(), json!(self.cap_drop));
+        }
+
+        // Add devices
+        if !self.devices.is_empty() {
+            let devices: Vec<JsonValue> = self
+                .devices
+                .iter()
+                .map(|device| {
+                    json!({
+                        "PathOnHost": device.host_path,
+                        "PathInContainer": device.container_path,
+                        "CgroupPermissions": device.permissions,
+                    })
+                })
+                .collect();
+            host_config.insert("Devices".into(), json!(devices));
+        }
+
+        // Set security options
+        if !self.security_opts.is_empty() {
+            host_config.insert("SecurityOpt".into(), json!(self.security_opts));
+        }
+
+        // Set health check. The API takes durations in nanoseconds.
+        if let Some(healthcheck) = &self.healthcheck {
+            let mut docker_healthcheck = JsonMap::new();
+            docker_healthcheck.insert("Test".into(), json!(["CMD-SHELL", healthcheck.cmd]));
+            let durations = [
+                ("Interval", healthcheck.interval),
+                ("Timeout", healthcheck.timeout),
+                ("StartPeriod", healthcheck.start_period),
+            ];
+            for (name, secs) in durations.iter() {
+                if let Some(secs) = secs {
+                    docker_healthcheck
+                        .insert((*name).into(), json!(secs.saturating_mul(1_000_000_000)));
+                }
+            }
+            if let Some(retries) = healthcheck.retries {
+                docker_healthcheck.insert("Retries".into(), json!(retries));
+            }
+            options.insert("Healthcheck".into(), docker_healthcheck.into());
         }
 
-        // Add volumes
-        options.volumes(volumes.iter().map(AsRef::as_ref).collect());
         // Add environment
-        options.env(env.iter().map(AsRef::as_ref).collect());
+        env.sort();
+        options.insert("Env".into(), json!(env));
+
+        // Set resource limits
+        host_config.extend(self.resources.to_api_fields());
+
+        // Set the restart policy
+        let (policy_name, max_retries) = match &self.restart_policy {
+            RestartPolicy::OnFailure { max_retries } => {
+                ("on-failure".to_string(), max_retries.unwrap_or(0))
+            }
+            policy => (policy.to_string(), 0 /* Maximum retry count */),
+        };
+        host_config.insert(
+            "RestartPolicy".into(),
+            json!({ "Name": policy_name, "MaximumRetryCount": max_retries }),
+        );
 
-        // TODO: Right now we will always add the "restart unless-stopped" flag, but we should
-        // parameterize this later.
-        options.restart_policy("unless-stopped", 0 /* Maximum retry count */);
+        options.insert("HostConfig".into(), host_config.into());
 
-        // Build options
-        Ok(options.build())
+        // Label the container with the hash of its options
+        let mut options: JsonValue = options.into();
+        let hash = format!("{:x}", Sha1::digest(options.to_string().as_bytes()));
+        options["Labels"][CONFIG_HASH_LABEL] = hash.into();
+
+        Ok(options)
     }
 }
 
-/// Make sure Docker is installed an available
-pub(crate) fn ensure_docker() -> anyhow::Result<()> {
-    // Skip if docker is already installed
-    if cmd_exists("docker", &["--version"])? {
-        return Ok(());
+/// Get the health status of a container
+///
+/// Returns `None` if the container does not have a health check.
+pub(crate) fn get_container_health(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+) -> anyhow::Result<Option<HealthStatus>> {
+    runtime
+        .inspect_container(container_id)?
+        .ok_or_else(|| anyhow::format_err!("Container not found: {}", container_id))?
+        .health_status()
+}
+
+/// The state of a container as reported by the container runtime
+pub(crate) struct ContainerStatus {
+    /// The container state, such as `running`, `exited`, or `restarting`
+    pub state: String,
+    /// The exit code of the container's last run
+    pub exit_code: i64,
+    /// The number of times the container has been restarted by its restart policy
+    pub restart_count: i64,
+    /// The time that the container was last started in RFC 3339 format, if it has been started
+    pub started_at: Option<String>,
+    /// The container health, if it has a health check
+    pub health: Option<HealthStatus>,
+    /// The id of the container's image
+    pub image_id: String,
+    /// The registry digest of the container's image, if it was pulled from a registry
+    pub image_digest: Option<String>,
+}
+
+/// Get the runtime state of a container
+///
+/// Returns `None` if the container does not exist in the container runtime.
+pub(crate) fn get_container_status(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+) -> anyhow::Result<Option<ContainerStatus>> {
+    let details = match runtime.inspect_container(container_id)? {
+        Some(details) => details,
+        None => return Ok(None),
+    };
+    let health = details.health_status()?;
+
+    // The repo digests are in the format `repository@digest`
+    let image_digest = runtime
+        .inspect_image(&details.image)?
+        .and_then(|image| image.repo_digests)
+        .unwrap_or_default()
+        .iter()
+        .find_map(|repo_digest| repo_digest.splitn(2, '@').nth(1).map(ToString::to_string));
+
+    Ok(Some(ContainerStatus {
+        state: details.state.status,
+        exit_code: details.state.exit_code,
+        restart_count: details.restart_count,
+        // Containers that have never been started have a zero start time
+        started_at: if details.state.started_at.starts_with("0001-") {
+            None
+        } else {
+            Some(details.state.started_at)
+        },
+        health,
+        image_id: details.image,
+        image_digest,
+    }))
+}
+
+/// Get the reference to the loaded image from the output of an image load
+///
+/// Docker prints `Loaded image: name:tag` for tagged images and `Loaded image ID: sha256:...` for
+/// untagged ones. Older versions of Podman print `Loaded image(s): name:tag` instead. If the
+/// tarball contains more than one image we use the last one.
+pub(crate) fn parse_loaded_image(output: &str) -> Option<&str> {
+    output.lines().rev().find_map(|line| {
+        let line = line.trim();
+        if line.starts_with("Loaded image ID: ") {
+            Some(line.trim_start_matches("Loaded image ID: "))
+        } else if line.starts_with("Loaded image: ") {
+            Some(line.trim_start_matches("Loaded image: "))
+        } else if line.starts_with("Loaded image(s): ") {
+            // Podman separates multiple images with commas
+            line.trim_start_matches("Loaded image(s): ")
+                .rsplit(',')
+                .next()
+        } else {
+            None
+        }
+    })
+}
+
+/// Update the resource limits of an existing container without re-creating it
+pub(crate) fn update_container_resources(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    resources: &ResourceLimits,
+) -> anyhow::Result<()> {
+    let mut options = resources.to_api_fields();
+    // The shared memory size can't be updated
+    options.remove("ShmSize");
+
+    runtime.update_container(container_id, &options.into())
+}
+
+/// Copy a file or directory from the host into a container, preserving permissions and ownership
+///
+/// This follows the same rules as `docker cp`: if the container path is an existing directory the
+/// source is copied into it, otherwise the source is copied to the container path.
+pub(crate) fn copy_to_container(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    host_path: &Path,
+    container_path: &str,
+) -> anyhow::Result<()> {
+    let host_metadata = fs::symlink_metadata(host_path)
+        .context(format!("Could not read host path: {}", host_path.display()))?;
+    let host_name = host_path
+        .file_name()
+        .ok_or_else(|| anyhow::format_err!("Invalid host path: {}", host_path.display()))?;
+
+    // Work out which directory to extract the archive in and what to name the copy
+    let (extract_dir, name) = match runtime.container_path_stat(container_id, container_path)? {
+        Some(stat) if stat.is_dir() => (container_path.to_string(), host_name.to_owned()),
+        Some(_) if host_metadata.is_dir() => {
+            bail!("Cannot copy a directory to a file: {}", container_path)
+        }
+        _ => {
+            let container_path = Path::new(container_path);
+            match (container_path.parent(), container_path.file_name()) {
+                (Some(parent), Some(name)) => (parent.to_string_lossy().into(), name.to_owned()),
+                _ => bail!("Invalid container path: {}", container_path.display()),
+            }
+        }
+    };
+
+    // Build an archive of the host path. Symlinks are copied as symlinks and the archive headers
+    // keep the uid, gid, and permissions of the files.
+    let mut archive = tar::Builder::new(Vec::new());
+    archive.follow_symlinks(false);
+    if host_metadata.is_dir() {
+        archive.append_dir_all(&name, host_path)?;
+    } else {
+        archive.append_path_with_name(host_path, &name)?;
+    }
+
+    runtime.put_archive(container_id, &extract_dir, archive.into_inner()?)
+}
+
+/// Copy a file or directory from a container to the host, preserving permissions and ownership
+///
+/// This follows the same rules as `docker cp`: if the host path is an existing directory the
+/// source is copied into it, otherwise the source is copied to the host path.
+pub(crate) fn copy_from_container(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    container_path: &str,
+    host_path: &Path,
+) -> anyhow::Result<()> {
+    let archive = runtime.get_archive(container_id, container_path)?;
+
+    if host_path.is_dir() {
+        unpack_archive(&archive, host_path, None)
+    } else {
+        match (host_path.parent(), host_path.file_name()) {
+            (Some(parent), Some(name)) => unpack_archive(&archive, parent, Some(name)),
+            _ => bail!("Invalid host path: {}", host_path.display()),
+        }
+    }
+}
+
+/// Unpack a tar archive into a directory, keeping the uid, gid, and permissions of the files
+///
+/// If `root_name` is given the root of the archive is renamed to it.
+fn unpack_archive(archive: &[u8], dir: &Path, root_name: Option<&OsStr>) -> anyhow::Result<()> {
+    let root_name = match root_name {
+        Some(root_name) => root_name,
+        None => return unpack_archive_in(archive, dir).map(|_| ()),
     };
 
+    // The paths of the entries can't be changed before they are unpacked, so the archive is
+    // unpacked into a staging directory next to the destination and its root is moved into place
+    let staging_dir = dir.join(format!(".lucky-cp-{:016x}", rand::random::<u64>()));
+    fs::create_dir(&staging_dir).context(format!(
+        "Could not create staging directory: {}",
+        staging_dir.display()
+    ))?;
+    let result = unpack_archive_in(archive, &staging_dir).and_then(|root| {
+        let root = root.ok_or_else(|| anyhow::format_err!("Archive is empty"))?;
+        let path = dir.join(root_name);
+        fs::rename(staging_dir.join(root), &path)
+            .context(format!("Could not copy to {}", path.display()))?;
+
+        Ok(())
+    });
+    fs::remove_dir_all(&staging_dir).ok();
+
+    result
+}
+
+/// Unpack a tar archive into a directory, returning the name of the root of the archive
+///
+/// The entries are unpacked with `tar`'s `unpack_in`, which refuses to write outside of the
+/// directory through symlinked parent directories or hard links.
+fn unpack_archive_in(archive: &[u8], dir: &Path) -> anyhow::Result<Option<OsString>> {
+    let mut root = None;
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        // Reject paths that leave the directory instead of skipping them like `unpack_in` does
+        let entry_path = entry.path()?.into_owned();
+        let mut path = PathBuf::new();
+        for component in entry_path.components() {
+            match component {
+                Component::Normal(component) => path.push(component),
+                Component::CurDir => (),
+                _ => bail!("Invalid path in archive: {}", entry_path.display()),
+            }
+        }
+        if root.is_none() {
+            root = path.iter().next().map(ToOwned::to_owned);
+        }
+
+        // `unpack_in` lets directories be unpacked over existing symlinks, which would change the
+        // permissions of the directory that the symlink points to
+        let is_symlink = |path: &Path| {
+            fs::symlink_metadata(path).map_or(false, |metadata| metadata.file_type().is_symlink())
+        };
+        if entry.header().entry_type().is_dir() && is_symlink(&dir.join(&path)) {
+            bail!(
+                "Cannot copy a directory over a symlink: {}",
+                dir.join(&path).display()
+            );
+        }
+
+        entry.set_preserve_permissions(true);
+        entry
+            .unpack_in(dir)
+            .context(format!("Could not unpack {}", entry_path.display()))?;
+
+        // Set the owner of the file. `unpack_in` has made sure that its parent is in the directory.
+        if path.iter().next().is_some() {
+            let path = dir.join(path);
+            let (uid, gid) = (entry.header().uid()?, entry.header().gid()?);
+            lchown(&path, Some(uid.try_into()?), Some(gid.try_into()?))
+                .context(format!("Could not set owner of {}", path.display()))?;
+        }
+    }
+
+    Ok(root)
+}
+
+/// Create a managed bridge network if it doesn't already exist
+pub(crate) fn ensure_network(
+    runtime: &dyn ContainerRuntime,
+    name: &str,
+    scope: NetworkScope,
+) -> anyhow::Result<()> {
+    let docker_name = scope.docker_network_name(name);
+
+    // Skip if the network already exists
+    if runtime
+        .list_networks()?
+        .iter()
+        .any(|network| network.name == docker_name)
+    {
+        return Ok(());
+    }
+
+    // Label the network so that we can find it again when garbage collecting networks
+    let unit_name = std::env::var("JUJU_UNIT_NAME").unwrap_or_default();
+    let mut labels = JsonMap::new();
+    labels.insert(NETWORK_LABEL.into(), name.into());
+    labels.insert(APP_LABEL.into(), app_name(&unit_name).into());
+    if scope == NetworkScope::Unit {
+        labels.insert(UNIT_LABEL.into(), unit_name.into());
+    }
+
+    log::debug!("Creating network: {}", docker_name);
+    runtime.create_network(&json!({
+        "Name": docker_name,
+        "Driver": "bridge",
+        "Labels": labels,
+        "CheckDuplicate": true,
+    }))?;
+
+    Ok(())
+}
+
+/// Connect a container to a managed network with the given DNS aliases
+pub(crate) fn connect_network(
+    runtime: &dyn ContainerRuntime,
+    container_id: &str,
+    name: &str,
+    attachment: &NetworkAttachment,
+) -> anyhow::Result<()> {
+    runtime.connect_network(
+        &attachment.scope.docker_network_name(name),
+        container_id,
+        &attachment.aliases,
+    )
+}
+
+/// Get the Docker names and scopes of the managed networks that belong to this unit
+///
+/// This includes the unit's own networks and the networks shared by the whole charm.
+pub(crate) fn list_managed_networks(
+    runtime: &dyn ContainerRuntime,
+) -> anyhow::Result<Vec<(String, NetworkScope)>> {
+    let unit_name = std::env::var("JUJU_UNIT_NAME").unwrap_or_default();
+    let app_name = app_name(&unit_name);
+
+    Ok(runtime
+        .list_networks()?
+        .into_iter()
+        .filter_map(|network| {
+            let labels = network.labels.unwrap_or_default();
+            if labels.get(APP_LABEL).map(String::as_str) != Some(app_name) {
+                return None;
+            }
+
+            // Skip networks that belong to other units of the charm
+            match labels.get(UNIT_LABEL) {
+                Some(network_unit) if network_unit == &unit_name => {
+                    Some((network.name, NetworkScope::Unit))
+                }
+                Some(_) => None,
+                None => Some((network.name, NetworkScope::Charm)),
+            }
+        })
+        .collect())
+}
+
+/// Returns `true` if there are containers on the host that belong to other units of the charm
+pub(crate) fn other_units_have_containers(runtime: &dyn ContainerRuntime) -> anyhow::Result<bool> {
+    let unit_name = std::env::var("JUJU_UNIT_NAME").unwrap_or_default();
+
+    Ok(runtime
+        .list_containers(&format!("{}={}", APP_LABEL, app_name(&unit_name)))?
+        .iter()
+        .any(|container| {
+            container
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(UNIT_LABEL))
+                != Some(&unit_name)
+        }))
+}
+
+/// Make sure Docker is installed and configured according to the `docker` settings from the
+/// `lucky.yaml`
+///
+/// This is idempotent and Docker will only be restarted if its configuration changed.
+pub(crate) fn ensure_docker(settings: &DockerSettings) -> anyhow::Result<()> {
     // Install Docker
-    // TODO: We need to figure out whether or not to use the Docker snap, which doesn't work on
-    // LXD. We will also want to support Centos, but we might just have to install different
-    // packages depending on the system.
-    run_cmd_with_retries(
-        "apt-get",
-        &["install", "-y", "docker.io"],
-        &Default::default(),
-    )?;
+    let (service, config_path) = match settings.install_method {
+        DockerInstallMethod::Apt => {
+            install_docker_apt(settings.version.as_deref())?;
+            ("docker", "/etc/docker/daemon.json")
+        }
+        DockerInstallMethod::Snap => {
+            install_docker_snap(settings.version.as_deref())?;
+            (
+                "snap.docker.dockerd",
+                "/var/snap/docker/current/config/daemon.json",
+            )
+        }
+        DockerInstallMethod::Preinstalled => {
+            if settings.version.is_some() {
+                bail!("The Docker version can't be set when Docker is preinstalled");
+            }
+            if !cmd_exists("docker", &["--version"])? {
+                bail!("Docker is expected to be preinstalled but it could not be found");
+            }
+            ("docker", "/etc/docker/daemon.json")
+        }
+    };
+
+    // Configure the Docker daemon
+    let mut changed = configure_service_proxy(service)?;
+    if let Some(daemon_config) = &settings.daemon_config {
+        changed |= write_docker_daemon_config(Path::new(config_path), daemon_config)?;
+    }
+
+    // Restart Docker to apply the new configuration
+    if changed {
+        log::info!("Docker configuration changed, restarting Docker");
+        restart_service(service)?;
+    }
+
+    Ok(())
+}
+
+/// Install the `docker.io` package with apt, optionally pinning the package version
+fn install_docker_apt(version: Option<&str>) -> anyhow::Result<()> {
+    if let Some(version) = version {
+        // Skip if the requested version is already installed
+        let installed_version = run_cmd(
+            "dpkg-query",
+            &["--show", "--showformat=${Version}", "docker.io"],
+        )
+        .unwrap_or_default();
+        if installed_version.trim() == version {
+            return Ok(());
+        }
+
+        // Install the requested version and hold it so that it isn't upgraded automatically
+        log::info!("Installing Docker version: {}", version);
+        run_cmd_with_retries(
+            "apt-get",
+            &[
+                "install",
+                "-y",
+                "--allow-downgrades",
+                &format!("docker.io={}", version),
+            ],
+            &Default::default(),
+        )?;
+        run_cmd("apt-mark", &["hold", "docker.io"])?;
+
+    // Skip if docker is already installed
+    } else if !cmd_exists("docker", &["--version"])? {
+        run_cmd_with_retries(
+            "apt-get",
+            &["install", "-y", "docker.io"],
+            &Default::default(),
+        )?;
+    }
+
     // Make sure docker is installed
     if !cmd_exists("docker", &["--version"])? {
         bail!("Could not install Docker");
     }
 
+    Ok(())
+}
+
+/// Install the `docker` snap, optionally from a specific channel
+///
+/// The Docker snap doesn't work inside of LXD containers.
+fn install_docker_snap(channel: Option<&str>) -> anyhow::Result<()> {
+    // Get the channel that the snap is tracking if it is installed. The output of `snap list` is
+    // a header followed by `name version rev tracking publisher notes`.
+    let tracking = run_cmd("snap", &["list", "docker"])
+        .ok()
+        .and_then(|output| {
+            output
+                .lines()
+                .nth(1)
+                .and_then(|line| line.split_whitespace().nth(3))
+                .map(ToString::to_string)
+        });
+
+    match (tracking, channel) {
+        // Skip if the snap is installed from the right channel
+        (Some(_), None) => return Ok(()),
+        (Some(tracking), Some(channel))
+            if tracking == channel || tracking == format!("latest/{}", channel) =>
+        {
+            return Ok(())
+        }
+        // Switch the snap to the requested channel
+        (Some(_), Some(channel)) => {
+            log::info!("Switching Docker snap to channel: {}", channel);
+            run_cmd_with_retries(
+                "snap",
+                &["refresh", "docker", &format!("--channel={}", channel)],
+                &Default::default(),
+            )?;
+        }
+        // Install the snap
+        (None, channel) => {
+            let mut args = vec!["install".to_string(), "docker".into()];
+            if let Some(channel) = channel {
+                args.push(format!("--channel={}", channel));
+            }
+            run_cmd_with_retries(
+                "snap",
+                args.iter()
+                    .map(AsRef::as_ref)
+                    .collect::<Vec<&str>>()
+                    .as_slice(),
+                &Default::default(),
+            )?;
+        }
+    }
+
+    // Make sure docker is installed
+    if !cmd_exists("docker", &["--version"])? {
+        bail!("Could not install Docker");
+    }
+
+    Ok(())
+}
+
+/// Write the Docker daemon config to the `daemon.json` file, returning whether or not the file was
+/// changed
+fn write_docker_daemon_config(path: &Path, config: &DockerDaemonConfig) -> anyhow::Result<bool> {
+    let config = serde_json::to_value(config)?;
+
+    // Skip writing the file if it already has the same settings
+    if let Ok(existing) = fs::read_to_string(path) {
+        if !daemon_config_changed(&existing, &config) {
+            return Ok(false);
+        }
+    }
+
+    log::debug!("Writing Docker daemon config: {}", path.to_string_lossy());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context(format!("Could not create Docker config dir: {:?}", parent))?;
+    }
+    fs::write(
+        path,
+        format!("{}\n", serde_json::to_string_pretty(&config)?),
+    )
+    .context(format!("Could not write Docker daemon config: {:?}", path))?;
+
+    Ok(true)
+}
+
+/// Get whether or not the existing contents of a Docker `daemon.json` file differ from the given
+/// config
+///
+/// The settings are compared as JSON so that formatting and key order don't count as changes.
+/// Contents that aren't valid JSON are always considered changed.
+fn daemon_config_changed(existing: &str, config: &JsonValue) -> bool {
+    serde_json::from_str::<JsonValue>(existing).ok().as_ref() != Some(config)
+}
+
+/// Configure a systemd service to use the proxy settings from the environment, returning whether
+/// or not the service's configuration was changed
+///
+/// The service must be restarted with `restart_service` for the settings to take effect.
+pub(crate) fn configure_service_proxy(service: &str) -> anyhow::Result<bool> {
     // Get proxy settings from environment
     let mut proxy_settings = String::new();
     if let Ok(http_proxy) = std::env::var("HTTP_PROXY").or_else(|_| std::env::var("http_proxy")) {
@@ -261,37 +1963,656 @@ pub(crate) fn ensure_docker() -> anyhow::Result<()> {
         proxy_settings.push_str(&format!("Environment=\"HTTPS_PROXY={}\"\n", https_proxy));
     }
 
-    // If there are any proxy settings
-    if proxy_settings != "" {
-        // Create the Docker service drop-in dir
-        let dropin_dir = "/etc/systemd/system/docker.service.d/";
-        fs::create_dir_all(dropin_dir).context(format!(
-            "Could not create docker service drop-in config dir: {:?}",
-            dropin_dir
-        ))?;
+    // Skip if there aren't any proxy settings
+    if proxy_settings.is_empty() {
+        return Ok(false);
+    }
+    let dropin = format!("[Service]\n{}", proxy_settings);
 
-        // Open the drop-in file
-        let file_path = "/etc/systemd/system/docker.service.d/http-proxy.conf";
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(file_path)
-            .context(format!(
-                "Could not open Docker service dropin file: {:?}",
-                file_path
-            ))?;
+    // Skip if the drop-in file is already up-to-date
+    let dropin_dir = format!("/etc/systemd/system/{}.service.d/", service);
+    let file_path = format!("{}http-proxy.conf", dropin_dir);
+    if fs::read_to_string(&file_path).ok().as_deref() == Some(dropin.as_str()) {
+        return Ok(false);
+    }
 
-        // Insert proxy settings
-        file.write_all(format!("[Service]\n{}", proxy_settings).as_bytes())?;
+    // Create the service drop-in dir
+    fs::create_dir_all(&dropin_dir).context(format!(
+        "Could not create {} service drop-in config dir: {:?}",
+        service, dropin_dir
+    ))?;
 
-        // Close file
-        drop(file);
+    // Write the drop-in file
+    fs::write(&file_path, dropin).context(format!(
+        "Could not write {} service dropin file: {:?}",
+        service, file_path
+    ))?;
 
-        // Reload Docker config
-        run_cmd("systemctl", &["daemon-reload"])?;
-        run_cmd("systemctl", &["restart", "docker"])?;
-    }
+    Ok(true)
+}
+
+/// Reload the systemd configuration and restart a service
+pub(crate) fn restart_service(service: &str) -> anyhow::Result<()> {
+    run_cmd("systemctl", &["daemon-reload"])?;
+    run_cmd("systemctl", &["restart", service])?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container_options(config: &ContainerConfig) -> JsonValue {
+        config
+            .to_container_options(
+                Path::new("/charm"),
+                Path::new("/lucky-data"),
+                Path::new("/run/lucky.sock"),
+            )
+            .expect("Could not get container options")
+    }
+
+    #[test]
+    fn container_options_include_healthcheck() {
+        let mut config = ContainerConfig::new("nginx");
+        config.healthcheck = Some(HealthCheck {
+            cmd: "curl -f http://localhost".into(),
+            interval: Some(5),
+            timeout: None,
+            retries: Some(2),
+            start_period: Some(10),
+        });
+
+        let options = container_options(&config);
+        assert_eq!(
+            options["Healthcheck"],
+            json!({
+                "Test": ["CMD-SHELL", "curl -f http://localhost"],
+                "Interval": 5_000_000_000u64,
+                "StartPeriod": 10_000_000_000u64,
+                "Retries": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn container_options_without_healthcheck() {
+        let options = container_options(&ContainerConfig::new("nginx"));
+        assert_eq!(options["Image"], json!("nginx"));
+        assert!(options.get("Healthcheck").is_none());
+        assert_eq!(
+            options["HostConfig"]["RestartPolicy"],
+            json!({ "Name": "unless-stopped", "MaximumRetryCount": 0 })
+        );
+    }
+
+    #[test]
+    fn container_options_include_stop_settings() {
+        let options = container_options(&ContainerConfig::new("nginx"));
+        assert!(options.get("StopSignal").is_none());
+        assert!(options.get("StopTimeout").is_none());
+
+        let mut config = ContainerConfig::new("nginx");
+        config.stop_signal = Some("SIGQUIT".into());
+        config.stop_timeout = Some(30);
+        let options = container_options(&config);
+        assert_eq!(options["StopSignal"], json!("SIGQUIT"));
+        assert_eq!(options["StopTimeout"], json!(30));
+        assert_eq!(config.stop_timeout(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn container_options_hash_is_stable() {
+        // Build the same config twice, inserting into the maps in a different order
+        let build_config = |order: Vec<u32>| {
+            let mut config = ContainerConfig::new("nginx");
+            for i in order {
+                config.env_vars.insert(format!("VAR_{}", i), i.to_string());
+                config
+                    .ports
+                    .insert(format!("{}:{}", 8000 + i, 80 + i).parse().unwrap());
+            }
+            config
+        };
+        let mut config = build_config((0..10).collect());
+        let reordered_config = build_config((0..10).rev().collect());
+        let hash = |options: &JsonValue| options["Labels"][CONFIG_HASH_LABEL].clone();
+
+        let options = container_options(&config);
+        assert!(hash(&options).is_string());
+        assert_eq!(hash(&options), hash(&container_options(&reordered_config)));
+
+        config.env_vars.insert("VAR_0".into(), "changed".into());
+        assert_ne!(hash(&options), hash(&container_options(&config)));
+    }
+
+    #[test]
+    fn parse_restart_policy() {
+        assert_eq!("no".parse::<RestartPolicy>().unwrap(), RestartPolicy::No);
+        assert_eq!(
+            "always".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::Always
+        );
+        assert_eq!(
+            "unless-stopped".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::UnlessStopped
+        );
+        assert_eq!(
+            "on-failure".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::OnFailure { max_retries: None }
+        );
+        assert_eq!(
+            "on-failure:5".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::OnFailure {
+                max_retries: Some(5)
+            }
+        );
+    }
+
+    #[test]
+    fn restart_policy_round_trips_through_display() {
+        for policy in &[
+            "no",
+            "on-failure",
+            "on-failure:3",
+            "always",
+            "unless-stopped",
+        ] {
+            assert_eq!(
+                &policy.parse::<RestartPolicy>().unwrap().to_string(),
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid_restart_policies() {
+        assert!("sometimes".parse::<RestartPolicy>().is_err());
+        assert!("always:3".parse::<RestartPolicy>().is_err());
+        assert!("on-failure:".parse::<RestartPolicy>().is_err());
+        assert!("on-failure:-1".parse::<RestartPolicy>().is_err());
+        // Too large for the retry count
+        assert!("on-failure:99999999999999999999999"
+            .parse::<RestartPolicy>()
+            .is_err());
+    }
+
+    #[test]
+    fn resource_limits_api_fields() {
+        let limits = ResourceLimits {
+            memory: Some(512 * 1024 * 1024),
+            cpu_quota: Some(50_000),
+            shm_size: Some(64 * 1024 * 1024),
+            ..Default::default()
+        };
+
+        let options = container_options(&ContainerConfig {
+            resources: limits.clone(),
+            ..ContainerConfig::new("nginx")
+        });
+        assert_eq!(options["HostConfig"]["Memory"], json!(512 * 1024 * 1024));
+        assert_eq!(options["HostConfig"]["CpuQuota"], json!(50_000));
+        assert_eq!(options["HostConfig"]["ShmSize"], json!(64 * 1024 * 1024));
+        assert!(options["HostConfig"].get("MemorySwap").is_none());
+        assert_eq!(limits.to_api_fields().len(), 3);
+    }
+
+    #[test]
+    fn resource_limits_can_be_updated_in_place() {
+        let old = ResourceLimits {
+            memory: Some(1024),
+            cpu_shares: Some(512),
+            shm_size: Some(64),
+            ..Default::default()
+        };
+
+        // Changing and adding limits doesn't need a re-create
+        let new = ResourceLimits {
+            memory: Some(2048),
+            pids_limit: Some(100),
+            ..old.clone()
+        };
+        assert!(new.can_update_from(&old));
+        assert!(old.can_update_from(&old));
+    }
+
+    #[test]
+    fn resource_limits_need_recreate() {
+        let old = ResourceLimits {
+            memory: Some(1024),
+            cpu_shares: Some(512),
+            shm_size: Some(64),
+            ..Default::default()
+        };
+
+        // Removed limits can't be reset by an update
+        let removed = ResourceLimits {
+            cpu_shares: None,
+            ..old.clone()
+        };
+        assert!(!removed.can_update_from(&old));
+
+        // The shared memory size can't be changed, added or removed by an update
+        let changed_shm = ResourceLimits {
+            shm_size: Some(128),
+            ..old.clone()
+        };
+        assert!(!changed_shm.can_update_from(&old));
+        let removed_shm = ResourceLimits {
+            shm_size: None,
+            ..old.clone()
+        };
+        assert!(!removed_shm.can_update_from(&old));
+        assert!(!old.can_update_from(&ResourceLimits {
+            shm_size: None,
+            ..old.clone()
+        }));
+    }
+
+    #[test]
+    fn parse_port_binding() {
+        let binding: PortBinding = "80:8080".parse().unwrap();
+        assert_eq!(binding.host_ip, None);
+        assert_eq!(binding.host_port, 80);
+        assert_eq!(binding.container_port, 8080);
+        assert_eq!(binding.port_count, 1);
+        assert_eq!(binding.protocol, "tcp");
+        assert_eq!(binding.to_string(), "80:8080/tcp");
+    }
+
+    #[test]
+    fn parse_port_binding_ranges() {
+        let binding: PortBinding = "8000-8009:9000-9009/udp".parse().unwrap();
+        assert_eq!(binding.host_ports(), 8000..=8009);
+        assert_eq!(binding.container_ports(), 9000..=9009);
+        assert_eq!(binding.port_count, 10);
+        assert_eq!(binding.protocol, "udp");
+        assert_eq!(binding.to_string(), "8000-8009:9000-9009/udp");
+
+        assert!("8000-8009:9000-9004".parse::<PortBinding>().is_err());
+        assert!("8009-8000:9009-9000".parse::<PortBinding>().is_err());
+    }
+
+    #[test]
+    fn parse_port_binding_host_ips() {
+        let binding: PortBinding = "127.0.0.1:80:8080".parse().unwrap();
+        assert_eq!(binding.host_ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(binding.to_string(), "127.0.0.1:80:8080/tcp");
+
+        let binding: PortBinding = "[::1]:80:8080/sctp".parse().unwrap();
+        assert_eq!(binding.host_ip.as_deref(), Some("::1"));
+        assert_eq!(binding.protocol, "sctp");
+        assert_eq!(binding.to_string(), "[::1]:80:8080/sctp");
+
+        assert!("300.0.0.1:80:8080".parse::<PortBinding>().is_err());
+        assert!("[::g]:80:8080".parse::<PortBinding>().is_err());
+    }
+
+    #[test]
+    fn parse_port_binding_rejects_invalid_input() {
+        for invalid in &[
+            "",
+            "80",
+            "80:",
+            ":8080",
+            "70000:8080",
+            "80:99999",
+            "80:8080/icmp",
+            "80:8080/",
+            "a:8080",
+            "80:8080:9090",
+        ] {
+            assert!(
+                invalid.parse::<PortBinding>().is_err(),
+                "Parsed invalid port binding: {:?}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn container_options_include_ports() {
+        let mut config = ContainerConfig::new("nginx");
+        for port in &[
+            "8080-8081:80-81/udp",
+            "127.0.0.1:8443:443",
+            "[::1]:8443:443",
+        ] {
+            config.ports.insert(port.parse().unwrap());
+        }
+
+        let options = container_options(&config);
+        assert_eq!(
+            options["ExposedPorts"],
+            json!({ "80/udp": {}, "81/udp": {}, "443/tcp": {} })
+        );
+        assert_eq!(
+            options["HostConfig"]["PortBindings"],
+            json!({
+                "80/udp": [{ "HostIp": "", "HostPort": "8080" }],
+                "81/udp": [{ "HostIp": "", "HostPort": "8081" }],
+                "443/tcp": [
+                    { "HostIp": "127.0.0.1", "HostPort": "8443" },
+                    { "HostIp": "::1", "HostPort": "8443" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_bind_volumes() {
+        let (target, mount) = parse_volume("data:/var/lib/data").unwrap();
+        assert_eq!(target, VolumeTarget("/var/lib/data".into()));
+        assert_eq!(mount, VolumeMount::new(MountType::Bind, "data"));
+
+        let (target, mount) = parse_volume("/srv/config:/etc/app:ro,rshared").unwrap();
+        assert_eq!(target, VolumeTarget("/etc/app".into()));
+        assert_eq!(
+            mount,
+            VolumeMount {
+                read_only: true,
+                propagation: Some(MountPropagation::Rshared),
+                ..VolumeMount::new(MountType::Bind, "/srv/config")
+            }
+        );
+    }
+
+    #[test]
+    fn parse_named_volumes() {
+        let (target, mount) = parse_volume("app-data:/data:volume").unwrap();
+        assert_eq!(target, VolumeTarget("/data".into()));
+        assert_eq!(mount, VolumeMount::new(MountType::Volume, "app-data"));
+    }
+
+    #[test]
+    fn parse_tmpfs_volumes() {
+        // The source of a tmpfs mount is ignored
+        let (target, mount) = parse_volume("cache:/tmp/cache:tmpfs,size=64m").unwrap();
+        assert_eq!(target, VolumeTarget("/tmp/cache".into()));
+        assert_eq!(
+            mount,
+            VolumeMount {
+                tmpfs_size: Some(64 * 1024 * 1024),
+                ..VolumeMount::new(MountType::Tmpfs, "")
+            }
+        );
+    }
+
+    #[test]
+    fn reject_invalid_volumes() {
+        for volume in &[
+            "data",
+            "data:relative/path",
+            ":/data",
+            "data:/data:bogus",
+            "data:/data:size=64m",
+            "data:/data:volume,rshared",
+            "app/data:/data:volume",
+            "cache:/tmp/cache:tmpfs,size=lots",
+        ] {
+            assert!(parse_volume(volume).is_err(), "{}", volume);
+        }
+    }
+
+    #[test]
+    fn container_options_include_volumes() {
+        // The bind mount uses a host path that exists so that the test doesn't create it
+        let mut config = ContainerConfig::new("nginx");
+        for volume in &[
+            "/tmp:/etc/app:ro",
+            "app-data:/data:volume",
+            "cache:/tmp/cache:tmpfs,size=64m",
+        ] {
+            let (target, mount) = parse_volume(volume).unwrap();
+            config.volumes.insert(target, mount);
+        }
+
+        let options = container_options(&config);
+        let binds = options["HostConfig"]["Binds"].as_array().unwrap();
+        assert!(binds.contains(&json!("/tmp:/etc/app:ro")));
+        assert!(binds.contains(&json!("app-data:/data")));
+        assert_eq!(
+            options["HostConfig"]["Tmpfs"],
+            json!({ "/tmp/cache": "size=67108864" })
+        );
+    }
+
+    #[test]
+    fn parse_loaded_image_output() {
+        assert_eq!(
+            parse_loaded_image("Loaded image: example/app:1.0\n"),
+            Some("example/app:1.0")
+        );
+        assert_eq!(
+            parse_loaded_image("Loaded image ID: sha256:0123\n"),
+            Some("sha256:0123")
+        );
+        // The last image is used when the tarball contains more than one
+        assert_eq!(
+            parse_loaded_image("Loaded image: example/a:1\nLoaded image: example/b:2\n"),
+            Some("example/b:2")
+        );
+        assert_eq!(
+            parse_loaded_image("Loaded image(s): example/a:1,example/b:2\n"),
+            Some("example/b:2")
+        );
+        assert_eq!(
+            parse_loaded_image("Getting image source signatures\n"),
+            None
+        );
+    }
+
+    /// Get a directory for a test that is removed when the returned guard is dropped
+    fn test_dir(name: &str) -> (PathBuf, impl Drop) {
+        struct RemoveDir(PathBuf);
+        impl Drop for RemoveDir {
+            fn drop(&mut self) {
+                fs::remove_dir_all(&self.0).ok();
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("lucky-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        (dir.clone(), RemoveDir(dir))
+    }
+
+    /// Get a tar header owned by the owner of `dir` so that the tests don't need to run as root
+    fn test_header(dir: &Path, entry_type: tar::EntryType, size: u64) -> tar::Header {
+        use std::os::unix::fs::MetadataExt;
+        let owner = fs::metadata(dir).unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mode(0o640);
+        header.set_uid(owner.uid().into());
+        header.set_gid(owner.gid().into());
+        header
+    }
+
+    #[test]
+    fn unpack_archive_into_directory() {
+        let (dir, _guard) = test_dir("unpack");
+
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Regular, 5);
+        archive
+            .append_data(&mut header, "config/app.conf", &b"hello"[..])
+            .unwrap();
+        let archive = archive.into_inner().unwrap();
+
+        // The root of the archive keeps its name when unpacking into a directory
+        unpack_archive(&archive, &dir, None).unwrap();
+        assert_eq!(fs::read(dir.join("config/app.conf")).unwrap(), b"hello");
+
+        // And is renamed when copying to a new path
+        unpack_archive(&archive, &dir, Some(OsStr::new("renamed"))).unwrap();
+        let renamed = dir.join("renamed/app.conf");
+        assert_eq!(fs::read(&renamed).unwrap(), b"hello");
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&renamed).unwrap().permissions())
+                & 0o777,
+            0o640
+        );
+
+        // The staging directory is cleaned up
+        let mut entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["config", "renamed"]);
+    }
+
+    #[test]
+    fn unpack_archive_hard_link() {
+        let (dir, _guard) = test_dir("unpack-hard-link");
+
+        // Hard links are resolved against the archive, even when its root is renamed
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Regular, 5);
+        archive
+            .append_data(&mut header, "data/original", &b"hello"[..])
+            .unwrap();
+        let mut header = test_header(&dir, tar::EntryType::Link, 0);
+        header.set_link_name("data/original").unwrap();
+        archive
+            .append_data(&mut header, "data/link", &b""[..])
+            .unwrap();
+        let archive = archive.into_inner().unwrap();
+        unpack_archive(&archive, &dir, Some(OsStr::new("copy"))).unwrap();
+        assert_eq!(fs::read(dir.join("copy/link")).unwrap(), b"hello");
+
+        // Hard links to files outside of the directory are rejected
+        let outside = dir.join("outside");
+        fs::write(&outside, b"secret").unwrap();
+        let target = dir.join("target");
+        fs::create_dir(&target).unwrap();
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Link, 0);
+        header.set_link_name(&outside).unwrap();
+        archive.append_data(&mut header, "link", &b""[..]).unwrap();
+        let archive = archive.into_inner().unwrap();
+        assert!(unpack_archive(&archive, &target, None).is_err());
+        assert!(!target.join("link").exists());
+    }
+
+    #[test]
+    fn unpack_archive_symlinked_parent() {
+        let (dir, _guard) = test_dir("unpack-symlink");
+        let outside = dir.join("outside");
+        fs::create_dir(&outside).unwrap();
+        let target = dir.join("target");
+        fs::create_dir(&target).unwrap();
+
+        // Files can't be written through a symlink in the archive that points out of the directory
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Symlink, 0);
+        header.set_link_name(&outside).unwrap();
+        archive
+            .append_data(&mut header, "escape", &b""[..])
+            .unwrap();
+        let mut header = test_header(&dir, tar::EntryType::Regular, 5);
+        archive
+            .append_data(&mut header, "escape/file", &b"hello"[..])
+            .unwrap();
+        let archive = archive.into_inner().unwrap();
+        assert!(unpack_archive(&archive, &target, None).is_err());
+        assert!(!outside.join("file").exists());
+
+        // Or through a symlink that is already in the directory
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Directory, 0);
+        header.set_mode(0o777);
+        archive
+            .append_data(&mut header, "escape", &b""[..])
+            .unwrap();
+        let mut header = test_header(&dir, tar::EntryType::Regular, 5);
+        archive
+            .append_data(&mut header, "escape/file", &b"hello"[..])
+            .unwrap();
+        let archive = archive.into_inner().unwrap();
+        assert!(unpack_archive(&archive, &target, None).is_err());
+        assert!(!outside.join("file").exists());
+        assert_ne!(
+            std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&outside).unwrap().permissions())
+                & 0o777,
+            0o777
+        );
+    }
+
+    #[test]
+    fn unpack_archive_rejects_paths_outside_of_directory() {
+        let (dir, _guard) = test_dir("unpack-parent-dir");
+
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut header = test_header(&dir, tar::EntryType::Regular, 0);
+        // `append_data` refuses to write `..` so the path is set on the header directly
+        header.as_gnu_mut().unwrap().name[..9].copy_from_slice(b"../escape");
+        header.set_cksum();
+        archive.append(&header, &b""[..]).unwrap();
+        let archive = archive.into_inner().unwrap();
+
+        assert!(unpack_archive(&archive, &dir, None).is_err());
+        assert!(!dir.parent().unwrap().join("escape").exists());
+    }
+
+    #[test]
+    fn daemon_config_unchanged_when_settings_match() {
+        let config = json!({
+            "registry-mirrors": ["https://mirror.example.com"],
+            "log-driver": "json-file",
+        });
+
+        // Formatting and key order are ignored
+        assert!(!daemon_config_changed(
+            r#"{"log-driver":"json-file","registry-mirrors":["https://mirror.example.com"]}"#,
+            &config
+        ));
+        assert!(!daemon_config_changed(
+            &serde_json::to_string_pretty(&config).unwrap(),
+            &config
+        ));
+    }
+
+    #[test]
+    fn daemon_config_changed_when_settings_differ() {
+        let config = json!({
+            "registry-mirrors": ["https://mirror.example.com"],
+        });
+
+        assert!(daemon_config_changed(
+            r#"{"registry-mirrors":["https://other.example.com"]}"#,
+            &config
+        ));
+        assert!(daemon_config_changed(
+            r#"{"registry-mirrors":["https://mirror.example.com"],"debug":true}"#,
+            &config
+        ));
+        assert!(daemon_config_changed("{}", &config));
+        assert!(daemon_config_changed("", &config));
+        assert!(daemon_config_changed("not json", &config));
+    }
+
+    #[test]
+    fn write_docker_daemon_config_only_when_changed() {
+        let dir = std::env::temp_dir().join(format!("lucky-daemon-config-{}", std::process::id()));
+        let path = dir.join("docker/daemon.json");
+        let mut config = DockerDaemonConfig::default();
+        config
+            .insecure_registries
+            .push("registry.local:5000".into());
+
+        assert!(write_docker_daemon_config(&path, &config).unwrap());
+        assert!(!write_docker_daemon_config(&path, &config).unwrap());
+
+        config.log_driver = Some("journald".into());
+        assert!(write_docker_daemon_config(&path, &config).unwrap());
+        let written: JsonValue = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["log-driver"], "journald");
+        assert_eq!(written["insecure-registries"][0], "registry.local:5000");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
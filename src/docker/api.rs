@@ -0,0 +1,221 @@
+//! A client for the Docker-compatible container API provided by Docker and Podman
+use anyhow::format_err;
+use futures::{future, prelude::*};
+use hyper::{header, Body, Chunk, Client, Method, Request, StatusCode};
+use hyperlocal::UnixConnector;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::rt::block_on;
+
+/// A client for a Docker-compatible container API
+pub(crate) struct ApiClient {
+    http: Client<UnixConnector>,
+    /// The path to the API socket
+    socket: String,
+}
+
+/// A response from the container API
+pub(crate) struct ApiResponse {
+    pub status: StatusCode,
+    pub headers: header::HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// A streamed response body from the container API
+pub(crate) type ByteStream = Box<dyn Stream<Item = Chunk, Error = anyhow::Error> + Send>;
+
+/// The response to a request that creates a container or an exec instance
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct IdResponse {
+    pub id: String,
+}
+
+impl ApiClient {
+    pub fn new(socket: &str) -> Self {
+        ApiClient {
+            http: Client::builder()
+                .keep_alive(false)
+                .build(UnixConnector::new()),
+            socket: socket.into(),
+        }
+    }
+
+    /// The path to the API socket
+    pub fn socket(&self) -> &str {
+        &self.socket
+    }
+
+    /// Build a request to the API
+    ///
+    /// The optional body is given along with its content type.
+    pub fn build_request(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<(&str, Body)>,
+        registry_auth: Option<&str>,
+    ) -> anyhow::Result<Request<Body>> {
+        let mut request = Request::builder();
+        request
+            .method(method)
+            .uri::<hyper::Uri>(hyperlocal::Uri::new(&self.socket, endpoint).into());
+        if let Some(registry_auth) = registry_auth {
+            request.header("X-Registry-Auth", registry_auth);
+        }
+
+        Ok(match body {
+            Some((content_type, body)) => request
+                .header(header::CONTENT_TYPE, content_type)
+                .body(body)?,
+            None => request.body(Body::empty())?,
+        })
+    }
+
+    /// Send a request to the API without checking the response status
+    pub fn send_request(&self, request: Request<Body>) -> anyhow::Result<ApiResponse> {
+        Ok(block_on(self.http.request(request).and_then(|response| {
+            let (parts, body) = response.into_parts();
+            body.concat2().map(move |body| ApiResponse {
+                status: parts.status,
+                headers: parts.headers,
+                body: body.to_vec(),
+            })
+        }))?)
+    }
+
+    /// Send a request to the API without checking the response status
+    ///
+    /// The optional body is given along with its content type.
+    pub fn send(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<(&str, Body)>,
+    ) -> anyhow::Result<ApiResponse> {
+        self.send_request(self.build_request(method, endpoint, body, None)?)
+    }
+
+    /// Send a request to the API, returning an error if the response status isn't successful
+    ///
+    /// The optional body is given along with its content type.
+    pub fn request(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<(&str, Body)>,
+    ) -> anyhow::Result<ApiResponse> {
+        let response = self.send(method.clone(), endpoint, body)?;
+        check_status(&method, endpoint, response)
+    }
+
+    /// Send a request to the API and stream the response body as it arrives, returning an error
+    /// if the response status isn't successful
+    ///
+    /// This is used for long running requests such as following container logs.
+    pub fn stream(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Option<(&str, Body)>,
+    ) -> anyhow::Result<ByteStream> {
+        let request = self.build_request(method.clone(), endpoint, body, None)?;
+        let endpoint = endpoint.to_string();
+
+        Ok(Box::new(
+            self.http
+                .request(request)
+                .from_err()
+                .and_then(move |response| {
+                    let (parts, body) = response.into_parts();
+                    if parts.status.is_success() {
+                        future::Either::A(future::ok(body))
+                    } else {
+                        // Read the error message from the body
+                        future::Either::B(body.concat2().from_err().and_then(move |body| {
+                            Err(response_error(
+                                &method,
+                                &endpoint,
+                                &ApiResponse {
+                                    status: parts.status,
+                                    headers: parts.headers,
+                                    body: body.to_vec(),
+                                },
+                            ))
+                        }))
+                    }
+                })
+                .map(|body| body.from_err())
+                .flatten_stream(),
+        ))
+    }
+
+    /// Send a GET request to the API and parse the JSON response, returning `None` if the requested
+    /// object doesn't exist
+    pub fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> anyhow::Result<Option<T>> {
+        let response = self.send(Method::GET, endpoint, None)?;
+        if response.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check_status(&Method::GET, endpoint, response)?;
+
+        Ok(Some(serde_json::from_slice(&response.body)?))
+    }
+
+    /// Send a POST request with a JSON body to the API
+    pub fn post(&self, endpoint: &str, body: &JsonValue) -> anyhow::Result<ApiResponse> {
+        self.request(
+            Method::POST,
+            endpoint,
+            Some(("application/json", serde_json::to_vec(body)?.into())),
+        )
+    }
+
+    /// Send a POST request with a JSON body to the API and parse the JSON response
+    pub fn post_json<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &JsonValue,
+    ) -> anyhow::Result<T> {
+        let response = self.post(endpoint, body)?;
+
+        Ok(serde_json::from_slice(&response.body)?)
+    }
+}
+
+/// Return an error with the message from the response body if the response status isn't successful
+pub(crate) fn check_status(
+    method: &Method,
+    endpoint: &str,
+    response: ApiResponse,
+) -> anyhow::Result<ApiResponse> {
+    if !response.status.is_success() {
+        return Err(response_error(method, endpoint, &response));
+    }
+
+    Ok(response)
+}
+
+/// Get the error for an unsuccessful response
+fn response_error(method: &Method, endpoint: &str, response: &ApiResponse) -> anyhow::Error {
+    // Error responses have a JSON body with a message
+    let message = serde_json::from_slice::<JsonValue>(&response.body)
+        .ok()
+        .and_then(|body| body.get("message")?.as_str().map(ToString::to_string))
+        .unwrap_or_else(|| String::from_utf8_lossy(&response.body).into_owned());
+
+    format_err!(
+        "{} {} failed ({}): {}",
+        method,
+        endpoint,
+        response.status,
+        message.trim()
+    )
+}
+
+/// Encode a value for use in the query string of an API request
+pub(crate) fn query_value(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
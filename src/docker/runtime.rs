@@ -0,0 +1,944 @@
+//! Container runtime backends that the daemon uses to run containers
+//!
+//! Docker and Podman both provide a Docker-compatible API. The operations that behave the same on
+//! both are implemented once on top of that API, and each backend implements the rest: Docker
+//! through shiplift and Podman through its API socket directly.
+use anyhow::{bail, format_err, Context};
+use chrono::{TimeZone, Utc};
+use futures::{prelude::*, stream};
+use hyper::{Body, Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use shiplift::{
+    builder::ExecContainerOptions, tty::StreamType, Docker, LogsOptions, PullOptions, RegistryAuth,
+};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+use super::api::{check_status, query_value, ApiClient, ApiResponse, IdResponse};
+use crate::docker::HealthStatus;
+use crate::process::{cmd_exists, run_cmd, run_cmd_with_retries};
+use crate::rt::block_on;
+use crate::types::{ContainerRuntimeKind, DockerSettings, LuckyMetadata};
+
+/// The path to the socket for Docker's API
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+/// The path to the socket for Podman's Docker-compatible API
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+/// The size of the chunks that files are streamed to the API in
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A chunk of output from a container's logs or from a command executed in a container
+pub(crate) struct OutputChunk {
+    /// The output
+    pub output: String,
+    /// Whether the output came from stderr instead of stdout
+    pub stderr: bool,
+}
+
+/// A stream of output from a container
+pub(crate) type OutputStream = Box<dyn Stream<Item = OutputChunk, Error = anyhow::Error> + Send>;
+
+/// Which of a container's logs to get
+pub(crate) struct LogOptions {
+    /// Keep streaming the logs as the container writes them
+    pub follow: bool,
+    /// Only get this many lines from the end of the logs
+    pub tail: Option<u64>,
+    /// Only get the logs written since this Unix timestamp
+    pub since: Option<i64>,
+}
+
+/// The details of a container as returned by the container API's inspect endpoint
+///
+/// Only the fields used by Lucky are included. Shiplift's version of this doesn't include the
+/// container's health.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerDetails {
+    pub id: String,
+    /// The id of the container's image
+    pub image: String,
+    pub state: ContainerState,
+    pub config: ContainerDetailsConfig,
+    /// The number of times the container has been restarted by its restart policy
+    #[serde(default)]
+    pub restart_count: i64,
+}
+
+impl ContainerDetails {
+    /// Get the health status of the container, returning `None` if it doesn't have a health check
+    pub fn health_status(&self) -> anyhow::Result<Option<HealthStatus>> {
+        // The health status is empty for containers without a health check
+        match &self.state.health {
+            Some(health) if !health.status.is_empty() => {
+                Ok(Some(health.status.parse().map_err(|_| {
+                    format_err!("Unrecognized container health status: {}", health.status)
+                })?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The runtime state of a container
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerState {
+    /// The container state, such as `running`, `exited`, or `restarting`
+    pub status: String,
+    pub running: bool,
+    pub restarting: bool,
+    pub exit_code: i64,
+    /// The time that the container was last started in RFC 3339 format. This is a zero time for
+    /// containers that have never been started.
+    pub started_at: String,
+    /// The result of the container's health check, if it has one. Older versions of Podman call
+    /// this `Healthcheck`.
+    #[serde(default, alias = "Healthcheck")]
+    pub health: Option<ContainerHealth>,
+}
+
+/// The health of a container with a health check
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerHealth {
+    /// The health status: `starting`, `healthy`, or `unhealthy`
+    pub status: String,
+}
+
+/// The configuration of a container as reported by the container API
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerDetailsConfig {
+    /// The image that the container was created from, as given when it was created
+    pub image: String,
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// The details of an image as returned by the container API's image inspect endpoint
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ImageDetails {
+    pub id: String,
+    /// The digests of the image in the registries that it was pulled from in the format
+    /// `repository@digest`
+    #[serde(default)]
+    pub repo_digests: Option<Vec<String>>,
+}
+
+/// A container as returned by the container API's container list endpoint
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerSummary {
+    pub id: String,
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// Information about a path in a container as returned by the container API's archive endpoint
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ContainerPathStat {
+    pub name: String,
+    /// The file mode and permission bits in the format used by Go's `os.FileMode`
+    pub mode: u32,
+}
+
+impl ContainerPathStat {
+    /// Returns `true` if the path is a directory
+    pub fn is_dir(&self) -> bool {
+        // Go's `os.ModeDir` is the most significant bit of the mode
+        self.mode & (1 << 31) != 0
+    }
+}
+
+/// The details of a network as returned by the container API's network list endpoint
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct NetworkDetails {
+    pub name: String,
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// The operations that the daemon needs from a container runtime
+///
+/// The operations with default implementations are sent to the runtime's Docker-compatible API
+/// with `api`.
+pub(crate) trait ContainerRuntime: Send + Sync {
+    /// The human readable name of the runtime
+    fn name(&self) -> &'static str;
+
+    /// The client for the runtime's Docker-compatible API
+    fn api(&self) -> &ApiClient;
+
+    /// Install the runtime on the host if it isn't already installed
+    fn ensure_installed(&self) -> anyhow::Result<()>;
+
+    /// Make sure that the runtime's API is reachable
+    fn check_connection(&self) -> anyhow::Result<()>;
+
+    /// Pull an image. The image must include a tag or digest.
+    fn pull_image(&self, image: &str, registry_auth: Option<RegistryAuth>) -> anyhow::Result<()>;
+
+    /// Start a container
+    fn start_container(&self, id: &str) -> anyhow::Result<()>;
+
+    /// Stop a container, killing it if it doesn't stop within `timeout`
+    fn stop_container(&self, id: &str, timeout: Duration) -> anyhow::Result<()>;
+
+    /// Remove a stopped container
+    fn remove_container(&self, id: &str) -> anyhow::Result<()>;
+
+    /// Execute a command in a running container with the given environment variables, in the
+    /// format `NAME=value`, and stream its output
+    fn exec(&self, id: &str, command: &[String], env: &[String]) -> anyhow::Result<OutputStream>;
+
+    /// Stream the logs of a container
+    fn logs(&self, id: &str, options: &LogOptions) -> anyhow::Result<OutputStream>;
+
+    /// Load the images in an image tarball, returning the reference to the last loaded image
+    ///
+    /// The tarball is streamed to the runtime so that large images aren't read into memory.
+    fn load_image(&self, path: &Path) -> anyhow::Result<String> {
+        let tarball = File::open(path)
+            .context(format!("Could not open image tarball: {}", path.display()))?;
+        let response = self.api().request(
+            Method::POST,
+            "/images/load",
+            Some((
+                "application/x-tar",
+                Body::wrap_stream(stream::iter_result(FileChunks(tarball))),
+            )),
+        )?;
+
+        let output = json_stream_output(&response.body)
+            .context(format!("Could not load image: {}", path.display()))?;
+        super::parse_loaded_image(&output)
+            .map(Into::into)
+            .ok_or_else(|| format_err!("Could not find loaded image in load output: {}", output))
+    }
+
+    /// Inspect a local image, returning `None` if the image isn't present on the host
+    fn inspect_image(&self, image: &str) -> anyhow::Result<Option<ImageDetails>> {
+        self.api().get_json(&format!("/images/{}/json", image))
+    }
+
+    /// Get the id of a local image, returning `None` if the image isn't present on the host
+    fn image_id(&self, image: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.inspect_image(image)?.map(|details| details.id))
+    }
+
+    /// Create a container from the body of an API create request, returning the new container's id
+    fn create_container(&self, options: &JsonValue) -> anyhow::Result<String> {
+        let response: IdResponse = self.api().post_json("/containers/create", options)?;
+
+        Ok(response.id)
+    }
+
+    /// Update the resource limits of a container from the body of an API update request
+    fn update_container(&self, id: &str, options: &JsonValue) -> anyhow::Result<()> {
+        self.api()
+            .post(&format!("/containers/{}/update", id), options)?;
+
+        Ok(())
+    }
+
+    /// Inspect a container, returning `None` if the container doesn't exist
+    fn inspect_container(&self, id: &str) -> anyhow::Result<Option<ContainerDetails>> {
+        self.api().get_json(&format!("/containers/{}/json", id))
+    }
+
+    /// List the containers on the host, including stopped containers, that have a label. The label
+    /// is given as `key=value`.
+    fn list_containers(&self, label: &str) -> anyhow::Result<Vec<ContainerSummary>> {
+        let filters = json!({ "label": [label] }).to_string();
+
+        Ok(self
+            .api()
+            .get_json(&format!(
+                "/containers/json?all=true&filters={}",
+                query_value(&filters)
+            ))?
+            .unwrap_or_default())
+    }
+
+    /// Execute a command in a running container, optionally writing `stdin` to its stdin, and
+    /// attach to its output
+    ///
+    /// Unlike `exec`, this makes the exit code of the command available once its output ends.
+    fn exec_attached(
+        &self,
+        id: &str,
+        command: &[String],
+        stdin: Option<String>,
+    ) -> anyhow::Result<AttachedExec> {
+        let exec: IdResponse = self.api().post_json(
+            &format!("/containers/{}/exec", id),
+            &json!({
+                "AttachStdin": stdin.is_some(),
+                "AttachStdout": true,
+                "AttachStderr": true,
+                "Cmd": command,
+            }),
+        )?;
+
+        // Starting the exec hijacks the connection to stream the command's input and output, which
+        // we can't get from the HTTP client, so we send the start request over the socket ourselves
+        let socket = self.api().socket();
+        let endpoint = format!("/exec/{}/start", exec.id);
+        let mut stream = UnixStream::connect(socket)
+            .context(format!("Could not connect to API socket: {}", socket))?;
+        let body = json!({ "Detach": false, "Tty": false }).to_string();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n{}",
+            endpoint,
+            body.len(),
+            body
+        )?;
+
+        // Read the response status and headers
+        let mut output = BufReader::new(stream.try_clone()?);
+        let mut status_line = String::new();
+        output.read_line(&mut status_line)?;
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| format_err!("Invalid exec start response: {}", status_line.trim()))?;
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if output.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            if parts
+                .next()
+                .unwrap_or("")
+                .eq_ignore_ascii_case("content-length")
+            {
+                content_length = parts.next().unwrap_or("").trim().parse().unwrap_or(0);
+            }
+        }
+
+        // The connection is only hijacked if the exec was started
+        if status != 101 {
+            let mut body = vec![0; content_length];
+            output.read_exact(&mut body)?;
+            check_status(
+                &Method::POST,
+                &endpoint,
+                ApiResponse {
+                    status: StatusCode::from_u16(status)?,
+                    headers: Default::default(),
+                    body,
+                },
+            )?;
+        }
+
+        // Write stdin to the command, closing our side of the connection when done so that the
+        // command's stdin is closed
+        if let Some(stdin) = stdin {
+            std::thread::spawn(move || {
+                stream
+                    .write_all(stdin.as_bytes())
+                    .and_then(|_| stream.shutdown(Shutdown::Write))
+                    .unwrap_or_else(|e| log::warn!("Could not write to exec stdin: {}", e));
+            });
+        }
+
+        Ok(AttachedExec {
+            output,
+            exec_id: exec.id,
+            socket: socket.into(),
+        })
+    }
+
+    /// Get information about a path in a container, returning `None` if the path doesn't exist
+    fn container_path_stat(
+        &self,
+        id: &str,
+        path: &str,
+    ) -> anyhow::Result<Option<ContainerPathStat>> {
+        let endpoint = archive_endpoint(id, path);
+        let response = self.api().send(Method::HEAD, &endpoint, None)?;
+        if response.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check_status(&Method::HEAD, &endpoint, response)?;
+
+        // The information is sent as base64 encoded JSON in a header
+        let stat = response
+            .headers
+            .get("X-Docker-Container-Path-Stat")
+            .ok_or_else(|| {
+                format_err!("Missing path stat header in response to HEAD {}", endpoint)
+            })?;
+
+        Ok(Some(serde_json::from_slice(&base64::decode(
+            stat.as_bytes(),
+        )?)?))
+    }
+
+    /// Extract a tar archive into a directory in a container. The directory must exist.
+    fn put_archive(&self, id: &str, path: &str, archive: Vec<u8>) -> anyhow::Result<()> {
+        self.api().request(
+            Method::PUT,
+            &archive_endpoint(id, path),
+            Some(("application/x-tar", archive.into())),
+        )?;
+
+        Ok(())
+    }
+
+    /// Get a tar archive of a file or directory in a container
+    ///
+    /// The root of the archive is named after the last component of the path.
+    fn get_archive(&self, id: &str, path: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(self
+            .api()
+            .request(Method::GET, &archive_endpoint(id, path), None)?
+            .body)
+    }
+
+    /// List the networks on the host
+    fn list_networks(&self) -> anyhow::Result<Vec<NetworkDetails>> {
+        Ok(self.api().get_json("/networks")?.unwrap_or_default())
+    }
+
+    /// Create a network from the body of an API network create request
+    fn create_network(&self, options: &JsonValue) -> anyhow::Result<()> {
+        self.api().post("/networks/create", options)?;
+
+        Ok(())
+    }
+
+    /// Connect a container to a network with the given DNS aliases
+    fn connect_network(
+        &self,
+        network: &str,
+        container_id: &str,
+        aliases: &[String],
+    ) -> anyhow::Result<()> {
+        self.api().post(
+            &format!("/networks/{}/connect", network),
+            &json!({
+                "Container": container_id,
+                "EndpointConfig": { "Aliases": aliases },
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove a network
+    fn remove_network(&self, network: &str) -> anyhow::Result<()> {
+        self.api()
+            .request(Method::DELETE, &format!("/networks/{}", network), None)?;
+
+        Ok(())
+    }
+
+    /// Remove a named volume
+    fn remove_volume(&self, name: &str) -> anyhow::Result<()> {
+        self.api()
+            .request(Method::DELETE, &format!("/volumes/{}", name), None)?;
+
+        Ok(())
+    }
+}
+
+/// Create the container runtime selected in the `lucky.yaml`
+///
+/// This doesn't connect to the runtime so that it can be used to install the runtime.
+pub(crate) fn new_runtime(lucky_metadata: &LuckyMetadata) -> Box<dyn ContainerRuntime> {
+    match lucky_metadata.container_runtime {
+        ContainerRuntimeKind::Docker => Box::new(DockerRuntime::new(lucky_metadata.docker.clone())),
+        ContainerRuntimeKind::Podman => Box::new(PodmanRuntime::new()),
+    }
+}
+
+/// The Docker container runtime
+pub(crate) struct DockerRuntime {
+    /// The shiplift client
+    docker: Docker,
+    /// A client for the API requests that shiplift doesn't support
+    api: ApiClient,
+    /// How Docker should be installed and configured
+    settings: DockerSettings,
+}
+
+impl DockerRuntime {
+    fn new(settings: DockerSettings) -> Self {
+        DockerRuntime {
+            docker: Docker::unix(DOCKER_SOCKET),
+            api: ApiClient::new(DOCKER_SOCKET),
+            settings,
+        }
+    }
+}
+
+impl ContainerRuntime for DockerRuntime {
+    fn name(&self) -> &'static str {
+        "Docker"
+    }
+
+    fn api(&self) -> &ApiClient {
+        &self.api
+    }
+
+    fn ensure_installed(&self) -> anyhow::Result<()> {
+        super::ensure_docker(&self.settings)
+    }
+
+    fn check_connection(&self) -> anyhow::Result<()> {
+        log::trace!("Docker info: {:?}", block_on(self.docker.info())?);
+
+        Ok(())
+    }
+
+    fn pull_image(&self, image: &str, registry_auth: Option<RegistryAuth>) -> anyhow::Result<()> {
+        let mut pull_options = PullOptions::builder();
+        pull_options.image(image);
+        if let Some(registry_auth) = registry_auth {
+            pull_options.auth(registry_auth);
+        }
+
+        block_on(self.docker.images().pull(&pull_options.build()).collect())?;
+
+        Ok(())
+    }
+
+    fn start_container(&self, id: &str) -> anyhow::Result<()> {
+        block_on(self.docker.containers().get(id).start())?;
+
+        Ok(())
+    }
+
+    fn stop_container(&self, id: &str, timeout: Duration) -> anyhow::Result<()> {
+        block_on(self.docker.containers().get(id).stop(Some(timeout)))?;
+
+        Ok(())
+    }
+
+    fn remove_container(&self, id: &str) -> anyhow::Result<()> {
+        block_on(self.docker.containers().get(id).delete())?;
+
+        Ok(())
+    }
+
+    fn exec(&self, id: &str, command: &[String], env: &[String]) -> anyhow::Result<OutputStream> {
+        let options = ExecContainerOptions::builder()
+            .attach_stderr(true)
+            .attach_stdout(true)
+            .env(env.iter().map(AsRef::as_ref).collect())
+            .cmd(command.iter().map(AsRef::as_ref).collect())
+            .build();
+
+        Ok(Box::new(
+            self.docker
+                .containers()
+                .get(id)
+                .exec(&options)
+                .map(|chunk| OutputChunk {
+                    output: chunk.as_string_lossy(),
+                    stderr: chunk.stream_type == StreamType::StdErr,
+                })
+                .from_err(),
+        ))
+    }
+
+    fn logs(&self, id: &str, options: &LogOptions) -> anyhow::Result<OutputStream> {
+        let mut logs_options = LogsOptions::builder();
+        logs_options
+            .stdout(true)
+            .stderr(true)
+            .follow(options.follow);
+        logs_options.tail(&options.tail.map_or("all".into(), |x| x.to_string()));
+        if let Some(since) = options.since {
+            logs_options.since(&Utc.timestamp(since, 0));
+        }
+
+        Ok(Box::new(
+            self.docker
+                .containers()
+                .get(id)
+                .logs(&logs_options.build())
+                .map(|chunk| OutputChunk {
+                    output: chunk.as_string_lossy(),
+                    stderr: chunk.stream_type == StreamType::StdErr,
+                })
+                .from_err(),
+        ))
+    }
+}
+
+/// The Podman container runtime
+///
+/// Podman is driven through the Docker-compatible API provided by the `podman.socket` systemd
+/// unit.
+pub(crate) struct PodmanRuntime {
+    api: ApiClient,
+}
+
+impl PodmanRuntime {
+    fn new() -> Self {
+        PodmanRuntime {
+            api: ApiClient::new(PODMAN_SOCKET),
+        }
+    }
+}
+
+impl ContainerRuntime for PodmanRuntime {
+    fn name(&self) -> &'static str {
+        "Podman"
+    }
+
+    fn api(&self) -> &ApiClient {
+        &self.api
+    }
+
+    fn ensure_installed(&self) -> anyhow::Result<()> {
+        ensure_podman()
+    }
+
+    fn check_connection(&self) -> anyhow::Result<()> {
+        self.api.request(Method::GET, "/_ping", None)?;
+
+        Ok(())
+    }
+
+    fn pull_image(&self, image: &str, registry_auth: Option<RegistryAuth>) -> anyhow::Result<()> {
+        let endpoint = format!("/images/create?fromImage={}", query_value(image));
+        let request = self.api.build_request(
+            Method::POST,
+            &endpoint,
+            None,
+            registry_auth.map(|auth| auth.serialize()).as_deref(),
+        )?;
+        let response = check_status(&Method::POST, &endpoint, self.api.send_request(request)?)?;
+
+        // Pull errors are reported in the progress messages
+        json_stream_output(&response.body).context(format!("Could not pull image: {}", image))?;
+
+        Ok(())
+    }
+
+    fn start_container(&self, id: &str) -> anyhow::Result<()> {
+        self.api
+            .request(Method::POST, &format!("/containers/{}/start", id), None)?;
+
+        Ok(())
+    }
+
+    fn stop_container(&self, id: &str, timeout: Duration) -> anyhow::Result<()> {
+        self.api.request(
+            Method::POST,
+            &format!("/containers/{}/stop?t={}", id, timeout.as_secs()),
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    fn remove_container(&self, id: &str) -> anyhow::Result<()> {
+        self.api
+            .request(Method::DELETE, &format!("/containers/{}", id), None)?;
+
+        Ok(())
+    }
+
+    fn exec(&self, id: &str, command: &[String], env: &[String]) -> anyhow::Result<OutputStream> {
+        let exec: IdResponse = self.api.post_json(
+            &format!("/containers/{}/exec", id),
+            &json!({
+                "AttachStdout": true,
+                "AttachStderr": true,
+                "Env": env,
+                "Cmd": command,
+            }),
+        )?;
+
+        let output = self.api.stream(
+            Method::POST,
+            &format!("/exec/{}/start", exec.id),
+            Some((
+                "application/json",
+                json!({ "Detach": false, "Tty": false }).to_string().into(),
+            )),
+        )?;
+
+        Ok(demux_output(output))
+    }
+
+    fn logs(&self, id: &str, options: &LogOptions) -> anyhow::Result<OutputStream> {
+        let mut endpoint = format!(
+            "/containers/{}/logs?stdout=true&stderr=true&follow={}&tail={}",
+            id,
+            options.follow,
+            options.tail.map_or("all".into(), |x| x.to_string())
+        );
+        if let Some(since) = options.since {
+            endpoint.push_str(&format!("&since={}", since));
+        }
+
+        Ok(demux_output(self.api.stream(
+            Method::GET,
+            &endpoint,
+            None,
+        )?))
+    }
+}
+
+/// Get the endpoint of the archive of a path in a container
+fn archive_endpoint(id: &str, path: &str) -> String {
+    format!("/containers/{}/archive?path={}", id, query_value(path))
+}
+
+/// Install Podman, pass the proxy settings on to it and enable its API socket
+fn ensure_podman() -> anyhow::Result<()> {
+    // Install Podman if it isn't already installed
+    if !cmd_exists("podman", &["--version"])? {
+        run_cmd_with_retries("apt-get", &["install", "-y", "podman"], &Default::default())?;
+
+        // Make sure Podman is installed
+        if !cmd_exists("podman", &["--version"])? {
+            bail!("Could not install Podman");
+        }
+    }
+
+    // Pass the proxy settings on to the Podman API service, which is what pulls the images
+    if super::configure_service_proxy("podman")? {
+        super::restart_service("podman")?;
+    }
+
+    // Enable the API socket
+    if !Path::new(PODMAN_SOCKET).exists() {
+        run_cmd("systemctl", &["enable", "--now", "podman.socket"])?;
+    }
+
+    Ok(())
+}
+
+/// A command executing in a container with its output attached
+///
+/// Iterating over this yields the command's output until the command exits.
+pub(crate) struct AttachedExec {
+    /// The hijacked API connection that the command's output is read from
+    output: BufReader<UnixStream>,
+    /// The id of the exec instance
+    exec_id: String,
+    /// The path to the API socket, used to get the command's exit code
+    socket: String,
+}
+
+impl AttachedExec {
+    /// Read the next chunk of output, returning `None` once the output ends
+    fn read_chunk(&mut self) -> std::io::Result<Option<OutputChunk>> {
+        let mut header = [0; 8];
+        match self.output.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let (stderr, size) = parse_output_header(&header);
+        let mut output = vec![0; size];
+        self.output.read_exact(&mut output)?;
+
+        Ok(Some(OutputChunk {
+            output: String::from_utf8_lossy(&output).into_owned(),
+            stderr,
+        }))
+    }
+
+    /// Get the exit code of the command. This should be called once the output has ended.
+    ///
+    /// The output can end a moment before the runtime records that the command has exited, so
+    /// this waits until the exec instance is no longer running.
+    pub fn exit_code(self) -> anyhow::Result<i64> {
+        let api = ApiClient::new(&self.socket);
+        let endpoint = format!("/exec/{}/json", self.exec_id);
+
+        loop {
+            let details: ExecDetails = api
+                .get_json(&endpoint)?
+                .ok_or_else(|| format_err!("Exec instance not found: {}", self.exec_id))?;
+            if !details.running {
+                return details.exit_code.ok_or_else(|| {
+                    format_err!("Exec instance has no exit code: {}", self.exec_id)
+                });
+            }
+
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Iterator for AttachedExec {
+    type Item = std::io::Result<OutputChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_chunk().transpose()
+    }
+}
+
+/// The details of an exec instance as returned by the container API
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExecDetails {
+    running: bool,
+    exit_code: Option<i64>,
+}
+
+/// Parse the header of a chunk of multiplexed container output, returning whether the chunk is
+/// from stderr and the size of the chunk
+///
+/// Without a TTY, container output is multiplexed with each chunk prefixed by an 8 byte header
+/// containing the stream type and the size of the chunk.
+fn parse_output_header(header: &[u8]) -> (bool, usize) {
+    let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+    (header[0] == 2, size as usize)
+}
+
+/// Split a stream of multiplexed container output into chunks of output
+fn demux_output(
+    output: impl Stream<Item = hyper::Chunk, Error = anyhow::Error> + Send + 'static,
+) -> OutputStream {
+    let mut demuxer = OutputDemuxer::default();
+
+    Box::new(
+        output
+            .map(move |data| stream::iter_ok(demuxer.push(&data)))
+            .flatten(),
+    )
+}
+
+/// Splits multiplexed container output into chunks of output
+///
+/// The output chunks can be split across the chunks of data read from the API, so the data is
+/// buffered until a whole output chunk has been read.
+#[derive(Default)]
+struct OutputDemuxer {
+    buffer: Vec<u8>,
+}
+
+impl OutputDemuxer {
+    /// Add data read from the API, returning the output chunks that have been completed
+    fn push(&mut self, data: &[u8]) -> Vec<OutputChunk> {
+        self.buffer.extend_from_slice(data);
+
+        let mut chunks = vec![];
+        while self.buffer.len() >= 8 {
+            let (stderr, size) = parse_output_header(&self.buffer[..8]);
+            if self.buffer.len() < 8 + size {
+                break;
+            }
+
+            let chunk: Vec<u8> = self.buffer.drain(..8 + size).skip(8).collect();
+            chunks.push(OutputChunk {
+                output: String::from_utf8_lossy(&chunk).into_owned(),
+                stderr,
+            });
+        }
+
+        chunks
+    }
+}
+
+/// An iterator over the chunks of a file, used to stream files to the API
+struct FileChunks(File);
+
+impl Iterator for FileChunks {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0; FILE_CHUNK_SIZE];
+        match self.0.read(&mut chunk) {
+            Ok(0) => None,
+            Ok(size) => {
+                chunk.truncate(size);
+                Some(Ok(chunk))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Get the output from the stream of JSON messages in the response to an image load or pull
+///
+/// For image loads the output is the same as the output of the runtime CLI's `load` command.
+fn json_stream_output(body: &[u8]) -> anyhow::Result<String> {
+    let mut output = String::new();
+    for message in serde_json::Deserializer::from_slice(body).into_iter::<JsonValue>() {
+        let message = message.context("Could not parse API response")?;
+        if let Some(error) = message.get("error").and_then(JsonValue::as_str) {
+            bail!("{}", error);
+        }
+        if let Some(stream) = message.get("stream").and_then(JsonValue::as_str) {
+            output.push_str(stream);
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_stream_output_joins_stream_messages() {
+        let body = br#"{"stream":"Loaded image: example/app:1.0\n"}
+{"stream":"Loaded image ID: sha256:0123\n"}"#;
+
+        assert_eq!(
+            json_stream_output(body).unwrap(),
+            "Loaded image: example/app:1.0\nLoaded image ID: sha256:0123\n"
+        );
+    }
+
+    #[test]
+    fn json_stream_output_returns_errors() {
+        let body = br#"{"status":"Pulling fs layer"}
+{"errorDetail":{"message":"unexpected EOF"},"error":"unexpected EOF"}"#;
+
+        assert_eq!(
+            json_stream_output(body).unwrap_err().to_string(),
+            "unexpected EOF"
+        );
+    }
+
+    #[test]
+    fn demuxer_buffers_split_chunks() {
+        let mut demuxer = OutputDemuxer::default();
+        let mut data = vec![1, 0, 0, 0, 0, 0, 0, 3];
+        data.extend_from_slice(b"out");
+        data.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 4]);
+        data.extend_from_slice(b"err\n");
+
+        // Split the data in the middle of the second chunk's header
+        let chunks = demuxer.push(&data[..13]);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].output, "out");
+        assert!(!chunks[0].stderr);
+
+        let chunks = demuxer.push(&data[13..]);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].output, "err\n");
+        assert!(chunks[0].stderr);
+        assert!(demuxer.buffer.is_empty());
+    }
+}
@@ -22,3 +22,15 @@ where
     let mut rt = RT.lock().unwrap();
     rt.block_on(future)
 }
+
+/// Spawn a future onto the tokio executor without waiting for it to complete
+///
+/// Unlike `block_on`, this doesn't hold onto the runtime while the future runs, so it can be used
+/// for long-running futures such as followed log streams.
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Send + 'static + Future<Item = (), Error = ()>,
+{
+    let mut rt = RT.lock().unwrap();
+    rt.spawn(future);
+}
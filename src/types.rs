@@ -1,6 +1,9 @@
 //! Types specific to Lucky that are used throughout the app
 
+use anyhow::format_err;
 use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -17,6 +20,12 @@ pub(crate) const LUCKY_EXIT_CODE_HELPER_PREFIX: &str = "__LUCKY_CMD_EXIT_CODE__:
 /// Juju related types
 pub(crate) mod juju;
 
+lazy_static! {
+    /// The regular expression for a byte size such as `512m`
+    static ref BYTE_SIZE_REGEX: Regex = Regex::new(r"^(?P<number>[0-9]+)(?P<unit>[bkmgBKMG])?$")
+        .expect("Could not compile regex");
+}
+
 #[derive(
     Copy,
     Clone,
@@ -123,12 +132,124 @@ pub(crate) struct LuckyMetadata {
     #[serde(default = "default_true")]
     /// Specifies whether or not to install Docker on the host and enable Docker-based features
     pub use_docker: bool,
+    /// The container runtime used to run the charm's containers
+    #[serde(default)]
+    pub container_runtime: ContainerRuntimeKind,
+    /// How Docker should be installed and configured
+    #[serde(default)]
+    pub docker: DockerSettings,
     /// The hooks for the charm
     #[serde(default)]
     pub hooks: HashMap<String, Vec<CharmScript>>,
     /// The cron jobs for the charm
     #[serde(default)]
     pub cron_jobs: IndexMap<String, Vec<CharmScript>>, // Use an IndexMap to preserve order
+    /// The containers for the charm by name. The `default` container is the default container.
+    #[serde(default)]
+    pub containers: HashMap<String, ContainerDefinition>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// The container runtime used to run containers
+pub(crate) enum ContainerRuntimeKind {
+    /// Docker, through the Docker daemon's API socket
+    Docker,
+    /// Podman, through Podman's Docker-compatible API socket
+    Podman,
+}
+
+impl Default for ContainerRuntimeKind {
+    fn default() -> Self {
+        Self::Docker
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+/// The `docker` section of the `lucky.yaml` file
+pub(crate) struct DockerSettings {
+    /// How to install Docker
+    #[serde(default)]
+    pub install_method: DockerInstallMethod,
+    /// The version of Docker to install. This is the package version for `apt` installs and the
+    /// snap channel for `snap` installs.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The settings to write to Docker's `daemon.json`. The file is left alone if this is not set.
+    #[serde(default)]
+    pub daemon_config: Option<DockerDaemonConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// The way that Docker is installed on the host
+pub(crate) enum DockerInstallMethod {
+    /// Install the `docker.io` package with apt
+    Apt,
+    /// Install the `docker` snap
+    Snap,
+    /// Don't install Docker and expect it to be installed already
+    Preinstalled,
+}
+
+impl Default for DockerInstallMethod {
+    fn default() -> Self {
+        Self::Apt
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+/// The Docker daemon settings managed by Lucky
+///
+/// This is serialized directly to Docker's `daemon.json` so the field names must match Docker's.
+pub(crate) struct DockerDaemonConfig {
+    /// Registry mirrors to pull images from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registry_mirrors: Vec<String>,
+    /// Registries that may be accessed without TLS
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub insecure_registries: Vec<String>,
+    /// The default logging driver for containers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_driver: Option<String>,
+    /// The options for the logging driver
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub log_opts: IndexMap<String, String>,
+    /// The storage driver
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_driver: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+/// A container declared in the `lucky.yaml` file
+pub(crate) struct ContainerDefinition {
+    /// The container image
+    pub image: String,
+    /// The container environment variables. Values may use Handlebars expressions such as
+    /// `{{config.port}}` or `{{kv.password}}` to include charm config and unit key-value data.
+    /// Config and keys that don't exist render as empty strings.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The container volumes in the format `source:target[:options]`, such as
+    /// `data:/var/lib/data:volume,ro`
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    /// The container port bindings in the format
+    /// `[host_ip:]host_port[-end]:container_port[-end][/protocol]`
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// The container network
+    #[serde(default)]
+    pub network: Option<String>,
+    /// The container command
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -185,6 +306,38 @@ pub(crate) enum CharmScriptType {
     },
 }
 
+/// Parse a byte size in the same format as the Docker CLI, such as `512m` or `2g`
+///
+/// The unit suffix may be `b`, `k`, `m`, or `g` and is interpreted in powers of 1024. A number
+/// without a suffix is taken as bytes.
+pub(crate) fn parse_byte_size(size: &str) -> anyhow::Result<i64> {
+    if let Some(captures) = BYTE_SIZE_REGEX.captures(size) {
+        let number: i64 = captures
+            .name("number")
+            .expect("Expected number")
+            .as_str()
+            .parse()
+            .map_err(|_| format_err!("Byte size is too large: {}", size))?;
+
+        let multiplier: i64 = match captures
+            .name("unit")
+            .map_or("b".into(), |x| x.as_str().to_lowercase())
+            .as_str()
+        {
+            "k" => 1024,
+            "m" => 1024 * 1024,
+            "g" => 1024 * 1024 * 1024,
+            _ => 1,
+        };
+
+        number
+            .checked_mul(multiplier)
+            .ok_or_else(|| format_err!("Byte size is too large: {}", size))
+    } else {
+        Err(format_err!("Could not parse byte size: {}", size))
+    }
+}
+
 //
 // Helpers
 //